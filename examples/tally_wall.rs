@@ -0,0 +1,65 @@
+//! Listens for TSL v3.1 packets and shows the latest state of every display seen, redrawn in
+//! place as packets arrive.
+//!
+//! ```sh
+//! cargo run --example tally_wall --features std -- 0.0.0.0:1234
+//! ```
+use std::{collections::BTreeMap, env, io::Write, net::UdpSocket};
+
+use tsl_umd::v3_1::{Brightness, PacketView, TSL31Packet};
+
+/// State of a single display, keyed by address in the wall
+struct Entry {
+    tally: [bool; 4],
+    brightness: Brightness,
+    display: String,
+}
+
+impl From<PacketView<'_>> for Entry {
+    fn from(view: PacketView<'_>) -> Self {
+        Self {
+            tally: view.tally,
+            brightness: view.brightness,
+            display: view.display.into(),
+        }
+    }
+}
+
+fn draw(wall: &BTreeMap<u8, Entry>, out: &mut impl Write) -> std::io::Result<()> {
+    // Move to the top left and clear the screen, so the wall updates in place
+    write!(out, "\x1b[H\x1b[2J")?;
+    writeln!(out, "addr  tally  brightness  display")?;
+    for (addr, entry) in wall {
+        let tally: String = entry
+            .tally
+            .iter()
+            .map(|&on| if on { '#' } else { '.' })
+            .collect();
+        writeln!(
+            out,
+            "{addr:>4}  {tally:<5}  {:<10}  {}",
+            format!("{:?}", entry.brightness),
+            entry.display
+        )?;
+    }
+    out.flush()
+}
+
+fn main() -> std::io::Result<()> {
+    let bind = env::args().nth(1).unwrap_or_else(|| "0.0.0.0:1234".into());
+    let sock = UdpSocket::bind(&bind)?;
+    let mut wall = BTreeMap::new();
+    let mut stdout = std::io::stdout();
+    let mut buf = [0u8; 1024];
+    draw(&wall, &mut stdout)?;
+    loop {
+        let (count, _) = sock.recv_from(&mut buf)?;
+        match TSL31Packet::from_datagram(&buf[..count]) {
+            Ok(packet) => {
+                wall.insert(packet.address(), packet.view().into());
+                draw(&wall, &mut stdout)?;
+            }
+            Err(e) => eprintln!("ignoring bad packet: {e}"),
+        }
+    }
+}
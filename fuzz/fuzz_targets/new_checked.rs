@@ -0,0 +1,78 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tsl_umd::v3_1::{FramingProfile, StreamDecoder, TSL31Packet, ValidationConfig};
+
+/// Call every getter. On a validated packet the display text must also be ascii
+fn exercise<T: AsRef<[u8]>>(p: &TSL31Packet<T>, validated: bool) {
+    if validated {
+        assert!(p.display_data().is_ascii());
+        assert!(p.display_data_untrimmed().is_ascii());
+    }
+    let _ = p.display_data();
+    let _ = p.display_data_untrimmed();
+    let _ = p.display_data_trimmed_with(0xFF);
+    let _ = p.display_data_raw();
+    let _ = p.display_chars().count();
+    let _ = p.display_remaining();
+    let _ = p.address();
+    let _ = p.control_byte();
+    let _ = p.tally();
+    let _ = p.brightness();
+    let _ = p.view();
+    let _ = p.diff(p);
+    assert!(p.eq_logical(p));
+    let _ = p.to_string();
+    let _ = p.summary().to_string();
+    let _ = p.hex_dump().to_string();
+    if validated {
+        // Only validated packets have a display that's guaranteed to fit a `TallyState`
+        let state = p.decode();
+        assert!(p.matches_state(&state));
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(p) = TSL31Packet::new_checked(data) {
+        exercise(&p, true);
+    }
+
+    // Every combination of validation options, picked by the first byte
+    let config = ValidationConfig {
+        allow_null_padding: data.first().is_some_and(|b| b & 1 != 0),
+        require_address_marker: data.first().is_some_and(|b| b & 2 != 0),
+    };
+    let checked = TSL31Packet::new_checked_with(data, config);
+    let parsed = TSL31Packet::parse_fields_with(data, config);
+    assert_eq!(checked.is_ok(), parsed.is_ok());
+    if let Ok(p) = checked {
+        exercise(&p, true);
+        assert_eq!(parsed, Ok(p.decode()));
+    }
+    let _ = TSL31Packet::parse_fields(data);
+
+    if let Ok(p) = TSL31Packet::from_datagram(data) {
+        exercise(&p, true);
+    }
+    for framing in [
+        FramingProfile::TrimLineEndings,
+        FramingProfile::IgnoreTrailing,
+        FramingProfile::LeadingSync,
+    ] {
+        if let Ok(p) = TSL31Packet::new_checked_framed(data, framing) {
+            exercise(&p, true);
+        }
+    }
+
+    // The getters must be safe on packets that were never validated, too
+    if data.len() >= 18 {
+        exercise(&TSL31Packet::new_unchecked(&data[..18]), false);
+    }
+
+    let mut decoder = StreamDecoder::new();
+    let mut input = data;
+    while let Some(p) = decoder.next_packet(&mut input) {
+        exercise(&p, true);
+    }
+    assert!(input.is_empty());
+});
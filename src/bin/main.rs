@@ -1,7 +1,15 @@
-use std::net::{IpAddr, UdpSocket};
+use std::{
+    fs::File,
+    io::{Read, stdin},
+    net::{IpAddr, UdpSocket},
+    path::PathBuf,
+    thread::sleep,
+    time::Duration,
+};
 
 use clap::{Parser, Subcommand, ValueEnum};
-use tsl_umd::v3_1::{Brightness as PBrightness, PACKET_LENGTH_31, TSL31Packet};
+use tsl_umd::hex::HexDump;
+use tsl_umd::v3_1::{Brightness as PBrightness, StreamDecoder, TSL31Packet, is_valid_display};
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 enum TslVersion {
@@ -35,6 +43,10 @@ struct Cli {
     #[arg(short, long, value_enum)]
     tsl_version: TslVersion,
 
+    /// Print packets as labelled lines, one per field
+    #[arg(short, long)]
+    verbose: bool,
+
     #[command(subcommand)]
     cmd: Commands,
 }
@@ -47,6 +59,10 @@ enum Commands {
 
         #[arg(short, long, default_value_t = 1234)]
         port: u16,
+
+        /// Join the multicast GROUP on the bind interface
+        #[arg(long, value_name = "GROUP")]
+        multicast: Option<IpAddr>,
     },
     Send {
         #[arg(short, long)]
@@ -63,30 +79,99 @@ enum Commands {
 
         #[arg(short, long, value_enum, default_value_t=Brightness::Full)]
         brightness: Brightness,
+
+        #[arg(short, long, default_value = "")]
+        display: String,
+
+        /// Allow sending to a broadcast or multicast address
+        #[arg(long)]
+        broadcast: bool,
+
+        /// Print the raw packet bytes as hex before sending
+        #[arg(long)]
+        dump: bool,
+
+        /// Resend the packet every INTERVAL milliseconds
+        #[arg(long)]
+        interval: Option<u64>,
+
+        /// Number of packets to send with --interval, 0 sends forever
+        #[arg(long, default_value_t = 0, requires = "interval")]
+        count: u64,
+    },
+    /// Decode raw packets, back to back, from stdin or a file
+    Decode {
+        /// Read from FILE instead of stdin
+        #[arg(short, long)]
+        file: Option<PathBuf>,
     },
 }
 
+/// Whether the address is multicast, or looks like a (limited or subnet-directed) broadcast
+/// address. Without the netmask we can't know the subnet's broadcast address, so any IPv4 address
+/// ending in .255 is accepted.
+fn is_broadcast_or_multicast(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_broadcast() || ip.is_multicast() || ip.octets()[3] == 255,
+        IpAddr::V6(ip) => ip.is_multicast(),
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Cli::parse();
     match args.cmd {
-        Commands::Listen { bind, port } => {
+        Commands::Listen {
+            bind,
+            port,
+            multicast,
+        } => {
+            if args.tsl_version != TslVersion::V3 {
+                return Err(
+                    format!("tsl {:?} packets aren't supported yet", args.tsl_version).into(),
+                );
+            }
+            if let Some(group) = multicast.filter(|g| !g.is_multicast()) {
+                return Err(format!("{group} is not a multicast address").into());
+            }
             println!(
                 "listening on {}:{} for tsl {:?} packets",
                 bind, port, args.tsl_version
             );
 
             let sock = UdpSocket::bind((bind, port))?;
+            match multicast {
+                None => {}
+                Some(IpAddr::V4(group)) => {
+                    let IpAddr::V4(interface) = bind else {
+                        return Err("an IPv4 multicast group needs an IPv4 bind address".into());
+                    };
+                    sock.join_multicast_v4(&group, &interface)?;
+                    println!("joined multicast group {group}");
+                }
+                Some(IpAddr::V6(group)) => {
+                    if !bind.is_ipv6() {
+                        return Err("an IPv6 multicast group needs an IPv6 bind address".into());
+                    }
+                    // Interface 0 lets the OS pick
+                    sock.join_multicast_v6(&group, 0)?;
+                    println!("joined multicast group {group}");
+                }
+            }
             loop {
                 let mut buf = [0u8; 1024];
                 let (count, remote) = sock.recv_from(&mut buf)?;
                 println!("got {} bytes from {}", count, remote);
-                println!("{:?}", &buf[0..count]);
+                println!("{}", HexDump::new(&buf[0..count]));
                 match args.tsl_version {
                     TslVersion::V3 => {
                         let packet = tsl_umd::v3_1::TSL31Packet::new_checked(&buf[0..count])?;
-                        println!("got packet {}", packet);
+                        if args.verbose {
+                            println!("got packet\n{}", packet.summary());
+                        } else {
+                            println!("got packet {}", packet);
+                        }
                     }
-                    _ => unimplemented!(),
+                    _ => unreachable!(),
                 }
             }
         }
@@ -96,11 +181,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             addr,
             tally,
             brightness,
+            display,
+            interval,
+            count,
+            broadcast,
+            dump,
         } => {
+            if broadcast && !is_broadcast_or_multicast(ip) {
+                return Err(format!("{ip} is not a broadcast or multicast address").into());
+            }
+            if display.len() > 16 {
+                return Err(format!(
+                    "display text must be at most 16 chars, got {}",
+                    display.len()
+                )
+                .into());
+            }
+            if !is_valid_display(&display) {
+                return Err("display text must be printable ascii only".into());
+            }
             let sock = UdpSocket::bind("0.0.0.0:0")?;
-            let buf = [0u8; PACKET_LENGTH_31];
-            let mut p = TSL31Packet::new_unchecked(buf);
-            p.set_address(addr).unwrap();
+            sock.set_broadcast(broadcast)?;
+            let mut p = TSL31Packet::new_zeroed();
+            p.set_address(addr)?;
             let state = [
                 tally.contains(&1),
                 tally.contains(&2),
@@ -110,7 +213,56 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             p.set_tally(state);
             p.set_brightness(brightness.into());
-            sock.send_to(&p.inner(), (ip, port))?;
+            p.set_display_data(&display);
+            if args.verbose {
+                println!("sending packet\n{}", p.summary());
+            } else {
+                println!("sending packet {}", p);
+            }
+            if dump {
+                println!("{}", p.hex_dump());
+            }
+            let buf = p.inner();
+            match interval {
+                None => {
+                    sock.send_to(&buf, (ip, port))?;
+                }
+                Some(interval) => {
+                    let mut sent = 0u64;
+                    while count == 0 || sent < count {
+                        if sent > 0 {
+                            sleep(Duration::from_millis(interval));
+                        }
+                        sock.send_to(&buf, (ip, port))?;
+                        sent += 1;
+                        println!("sent {sent} packets");
+                    }
+                }
+            }
+        }
+        Commands::Decode { file } => {
+            if args.tsl_version != TslVersion::V3 {
+                return Err(
+                    format!("tsl {:?} packets aren't supported yet", args.tsl_version).into(),
+                );
+            }
+            let mut bytes = Vec::new();
+            match file {
+                Some(path) => File::open(path)?.read_to_end(&mut bytes)?,
+                None => stdin().read_to_end(&mut bytes)?,
+            };
+            let mut decoder = StreamDecoder::new();
+            let mut count = 0;
+            let mut input = &bytes[..];
+            while let Some(packet) = decoder.next_packet(&mut input) {
+                count += 1;
+                if args.verbose {
+                    println!("got packet\n{}", packet.summary());
+                } else {
+                    println!("got packet {}", packet);
+                }
+            }
+            println!("decoded {count} packets from {} bytes", bytes.len());
         }
     }
     Ok(())
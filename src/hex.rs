@@ -0,0 +1,37 @@
+//! Allocation-free hex dumps of raw bytes, for diagnostics
+use core::fmt::Display;
+
+/// Formats bytes as space-separated hex, e.g. `81 32 00`, without allocating
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct HexDump<'a>(&'a [u8]);
+
+impl<'a> HexDump<'a> {
+    /// Wrap the given bytes for formatting
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl Display for HexDump<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, b) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for HexDump<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        for (i, b) in self.0.iter().enumerate() {
+            if i > 0 {
+                defmt::write!(f, " ");
+            }
+            defmt::write!(f, "{=u8:02x}", b);
+        }
+    }
+}
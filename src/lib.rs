@@ -4,7 +4,11 @@
 //! particularly on multiviewers/under monitor displays/broadcast monitors. It was originally
 //! used over serial, but is commonly also sent over IP. v5 explicitly supports this.
 //!
+//! Each protocol version is behind a cargo feature of the same name, so only the versions in use
+//! need to be compiled. `v3_1` is enabled by default.
+//!
 //! ```rust
+//! # #[cfg(feature = "v3_1")] {
 //!   use tsl_umd::v3_1::{TSL31Packet, PACKET_LENGTH_31};
 //!   // Build a new packet in a buffer:
 //!   let mut raw = [0u8; PACKET_LENGTH_31];
@@ -18,9 +22,17 @@
 //!   assert_eq!(packet.address(), 13);
 //!   assert_eq!(packet.display_data(), "hello");
 //!   assert!(packet.tally()[0]);
+//! # }
 //! ````
 #![no_std]
+pub mod hex;
+pub mod packet;
+#[cfg(all(feature = "heapless", feature = "v3_1"))]
+pub mod state;
+#[cfg(feature = "v3_1")]
 pub mod v3_1;
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
 #[cfg(feature = "std")]
 extern crate std;
@@ -0,0 +1,55 @@
+//! Items shared by all protocol versions
+
+/// TSL UMD protocol versions
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Version {
+    V3_1,
+    V4,
+    V5,
+}
+
+/// Whether a tally channel is on air, independent of how a protocol version represents it
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AirState {
+    OnAir,
+    Off,
+}
+
+impl From<bool> for AirState {
+    /// `true` is on air
+    fn from(lit: bool) -> Self {
+        if lit { Self::OnAir } else { Self::Off }
+    }
+}
+
+/// A packet in one of the TSL UMD protocol versions, for code that's generic over the version.
+///
+/// Versions differ in how they address displays and represent tally state, so this only exposes
+/// what they have in common: a numeric display address, display text and a number of tally
+/// channels, each of which is either lit or not. Version-specific detail (e.g. v3.1 brightness)
+/// is available from the packet types themselves.
+pub trait TslPacket {
+    /// The protocol version of the packet
+    const VERSION: Version;
+    /// The packet length in bytes. For versions with variable length packets this is the minimum
+    /// length.
+    const LENGTH: usize;
+    /// The number of tally channels per display
+    const TALLY_CHANNELS: usize;
+
+    /// The address of the display the packet is for
+    fn display_address(&self) -> u16;
+
+    /// The display text
+    fn display_text(&self) -> &str;
+
+    /// Whether the given tally channel (from 0) is lit, or `None` if it's out of range
+    fn tally_lit(&self, channel: usize) -> Option<bool>;
+
+    /// Whether the given tally channel (from 0) is on air, or `None` if it's out of range
+    fn air_state(&self, channel: usize) -> Option<AirState> {
+        self.tally_lit(channel).map(AirState::from)
+    }
+}
@@ -0,0 +1,31 @@
+//! Logical tally state, independent of any wire format
+use heapless::String;
+
+use crate::v3_1::{Brightness, Error, PACKET_LENGTH_31, TSL31Packet};
+
+/// The logical content of a tally packet, decoupled from how it's encoded on the wire.
+///
+/// Unlike the packet types this owns its data, so it can be stored, compared and diffed without
+/// holding on to a byte buffer.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TallyState {
+    /// The display address
+    pub address: u8,
+    /// Tally states, 4 channels
+    pub tally: [bool; 4],
+    /// Tally brightness
+    pub brightness: Brightness,
+    /// The display text, without any padding
+    pub display: String<16>,
+}
+
+impl TallyState {
+    /// Encode the state into a new v3.1 packet, with the display space-padded. See
+    /// [`TSL31Packet::apply`]
+    pub fn to_packet(&self) -> Result<TSL31Packet<[u8; PACKET_LENGTH_31]>, Error> {
+        let mut p = TSL31Packet::default();
+        p.apply(self)?;
+        Ok(p)
+    }
+}
@@ -1,21 +1,61 @@
 //! Version 3.1 implementation
-use core::{fmt::Display, ops::RangeInclusive};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::{
+    fmt::Display,
+    ops::{BitOr, RangeInclusive},
+};
+
+use crate::{
+    hex::HexDump,
+    packet::{TslPacket, Version},
+};
+
+#[cfg(feature = "heapless")]
+use crate::state::TallyState;
 
 /// TSL 3.1 packets are always 18 bytes long
 pub const PACKET_LENGTH_31: usize = 18;
 
+/// The address commonly treated as "all displays". It's the highest address that fits in the 7
+/// address bits, so valid addresses are `0x00..=BROADCAST_ADDRESS`.
+pub const BROADCAST_ADDRESS: u8 = 0x7F;
+
+/// The start of heading byte some implementations send before each packet, see
+/// [`FramingProfile::LeadingSync`]
+const SOH: u8 = 0x01;
+
 /// Range of values valid as display data (printable bytes)
 pub const VALID_DISPLAY: RangeInclusive<u8> = 0x20..=0x7F;
 
+/// Whether `s` can be used as display text: at most 16 bytes of [`VALID_DISPLAY`], optionally
+/// followed by null padding. See [`is_valid_display_bytes`]
+pub const fn is_valid_display(s: &str) -> bool {
+    is_valid_display_bytes(s.as_bytes())
+}
+
+/// Whether `bytes` are a valid display field, as validation checks: at most 16 bytes of
+/// [`VALID_DISPLAY`], optionally followed by null padding
+pub const fn is_valid_display_bytes(bytes: &[u8]) -> bool {
+    bytes.len() <= PACKET_LENGTH_31 - fields::DISPLAY_DATA.start && validate_display(bytes).is_ok()
+}
+
 /// A wrapper around a byte slice reference representing a TSL v3.1 Packet
-#[derive(Debug, PartialEq, Eq)]
+///
+/// Any buffer implementing `AsRef<[u8]>` can back a packet - e.g. an array, a slice, or an owned
+/// buffer like a `heapless::Vec` filled by DMA - and `AsMut<[u8]>` as well for the setters.
+#[derive(Debug, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct TSL31Packet<T: AsRef<[u8]>> {
     pub(crate) buf: T,
 }
 
-/// Tally light brightness, in 4 discrete steps
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+/// Tally light brightness, in 4 discrete steps.
+///
+/// v3.1 has no brightness byte: on the wire this is 2 bits of the CONTROL byte (see
+/// [`TSL31Packet::control_byte`]). To convert to and from a continuous intensity use the `f32`
+/// or percentage conversions.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Brightness {
     Zero,
@@ -24,14 +64,60 @@ pub enum Brightness {
     Full,
 }
 
-impl From<Brightness> for u8 {
-    /// The brightness value as a u8
+impl From<Brightness> for f32 {
+    /// The brightness as a normalized intensity, from 0.0 to 1.0
     fn from(val: Brightness) -> Self {
         match val {
-            Brightness::Zero => 0,
-            Brightness::OneSeventh => 36, // Approx
-            Brightness::OneHalf => 128,
-            Brightness::Full => 255,
+            Brightness::Zero => 0.0,
+            Brightness::OneSeventh => 1.0 / 7.0,
+            Brightness::OneHalf => 0.5,
+            Brightness::Full => 1.0,
+        }
+    }
+}
+
+impl Brightness {
+    /// All brightness steps, from dimmest to brightest
+    pub const fn all() -> [Self; 4] {
+        [Self::Zero, Self::OneSeventh, Self::OneHalf, Self::Full]
+    }
+
+    /// Snap a normalized intensity (0.0 to 1.0) to the nearest brightness step. Values exactly
+    /// halfway between two steps round up, values outside the range clamp and NaN is `Zero`.
+    pub fn from_f32_nearest(val: f32) -> Self {
+        let midpoint = |a: Self, b: Self| (f32::from(a) + f32::from(b)) / 2.0;
+        if val.is_nan() || val < midpoint(Self::Zero, Self::OneSeventh) {
+            Self::Zero
+        } else if val < midpoint(Self::OneSeventh, Self::OneHalf) {
+            Self::OneSeventh
+        } else if val < midpoint(Self::OneHalf, Self::Full) {
+            Self::OneHalf
+        } else {
+            Self::Full
+        }
+    }
+
+    /// The brightness as a percentage of full intensity: 0, 14, 50 or 100. This is for
+    /// presenting to users - on the wire brightness is a 2-bit step number, see
+    /// [`TSL31Packet::brightness_bits`].
+    pub const fn as_percent(self) -> u8 {
+        match self {
+            Self::Zero => 0,
+            Self::OneSeventh => 14,
+            Self::OneHalf => 50,
+            Self::Full => 100,
+        }
+    }
+
+    /// Snap a percentage to the nearest brightness step. Values exactly halfway between two
+    /// steps round up and values over 100 are `Full`.
+    pub const fn from_percent_nearest(percent: u8) -> Self {
+        // Midpoints between the steps' percentages
+        match percent {
+            0..7 => Self::Zero,
+            7..32 => Self::OneSeventh,
+            32..75 => Self::OneHalf,
+            _ => Self::Full,
         }
     }
 }
@@ -52,7 +138,7 @@ impl Display for Brightness {
 }
 
 /// Packet checking error
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// The first bit of the address isn't set - so it isn't a valid address
@@ -61,6 +147,15 @@ pub enum Error {
     BadLength { expected: usize, got: usize },
     /// Bad (non-ascii) bytes in the display data field.
     BadDisplayData { position: u8 },
+    /// Display data longer than the display field
+    DisplayTooLong { max: usize, got: usize },
+    /// Brightness bits out of the 2-bit range
+    BadBrightness { got: u8 },
+    /// An address over [`BROADCAST_ADDRESS`], which doesn't fit in the 7 address bits
+    AddressOutOfRange { got: u8 },
+    /// The high bit of the CONTROL byte is set. Only the address byte may have it set, as it
+    /// marks the start of a packet
+    ControlInvalid,
 }
 
 impl Display for Error {
@@ -73,6 +168,14 @@ impl Display for Error {
             Self::BadDisplayData { position } => {
                 write!(f, "BadDisplayData at position {position}")
             }
+            Self::DisplayTooLong { max, got } => {
+                write!(f, "DisplayTooLong: max {max}, got {got}")
+            }
+            Self::BadBrightness { got } => write!(f, "BadBrightness: expected 0..=3, got {got}"),
+            Self::AddressOutOfRange { got } => {
+                write!(f, "AddressOutOfRange: expected 0..=127, got {got}")
+            }
+            Self::ControlInvalid => write!(f, "ControlInvalid"),
         }
     }
 }
@@ -85,46 +188,371 @@ pub(crate) mod fields {
     pub(crate) const ADDRESS: usize = 0;
     pub(crate) const CONTROL: usize = 1;
     pub(crate) const DISPLAY_DATA: Range<usize> = 2..PACKET_LENGTH_31;
+
+    // The layout is fixed by the spec: one byte each of ADDRESS and CONTROL, then 16 of display
+    // data running to the end of the packet, with no gaps or overlaps
+    const _: () = assert!(ADDRESS == 0);
+    const _: () = assert!(CONTROL == ADDRESS + 1);
+    const _: () = assert!(DISPLAY_DATA.start == CONTROL + 1);
+    const _: () = assert!(DISPLAY_DATA.end == PACKET_LENGTH_31);
+    const _: () = assert!(DISPLAY_DATA.end - DISPLAY_DATA.start == 16);
 }
 
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(e: Error) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Tally states of the 4 channels, packed as in the CONTROL byte: channel 1 is bit 0 through to
+/// channel 4 in bit 3
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Tally(u8);
+
+impl Tally {
+    /// Tally states from the low 4 bits of the given byte. Other bits are ignored
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits & 0x0f)
+    }
+
+    /// The tally states packed into the low 4 bits
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// Tally states from one bool per channel
+    pub const fn from_array(state: [bool; 4]) -> Self {
+        Self(state[0] as u8 | (state[1] as u8) << 1 | (state[2] as u8) << 2 | (state[3] as u8) << 3)
+    }
+
+    /// The tally state as one bool per channel
+    pub const fn to_array(self) -> [bool; 4] {
+        [
+            self.0 & 0b1 != 0,
+            self.0 & 0b10 != 0,
+            self.0 & 0b100 != 0,
+            self.0 & 0b1000 != 0,
+        ]
+    }
+}
+
+impl From<[bool; 4]> for Tally {
+    fn from(state: [bool; 4]) -> Self {
+        Self::from_array(state)
+    }
+}
+
+impl From<Tally> for [bool; 4] {
+    fn from(tally: Tally) -> Self {
+        tally.to_array()
+    }
+}
+
+/// The CONTROL byte, with named flags for each tally channel and accessors for the brightness
+/// sub-field.
+///
+/// Bits 0-3 are tally channels 1-4 and bits 4-5 are the brightness. Bit 6 is reserved and bit 7
+/// is always clear, as validation rejects packets with it set: they're kept as-is, but not
+/// otherwise touched.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Control(u8);
+
+impl Control {
+    pub const TALLY_1: Self = Self(1 << 0);
+    pub const TALLY_2: Self = Self(1 << 1);
+    pub const TALLY_3: Self = Self(1 << 2);
+    pub const TALLY_4: Self = Self(1 << 3);
+    /// The bits holding the tally channels
+    pub const TALLY_MASK: u8 = 0x0f;
+    /// The bits holding the brightness
+    pub const BRIGHTNESS_MASK: u8 = 0x30;
+    const BRIGHTNESS_SHIFT: u32 = 4;
+
+    /// Wrap a raw CONTROL byte
+    pub const fn from_byte(b: u8) -> Self {
+        Self(b)
+    }
+
+    /// The raw CONTROL byte
+    pub const fn to_byte(self) -> u8 {
+        self.0
+    }
+
+    /// Whether all of the bits set in `other` are set
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The tally channels
+    pub const fn tally(self) -> Tally {
+        Tally::from_bits(self.0)
+    }
+
+    /// Replace the tally channels, keeping the other bits
+    pub const fn with_tally(self, tally: Tally) -> Self {
+        Self(self.0 & !Self::TALLY_MASK | tally.bits())
+    }
+
+    /// The raw 2-bit brightness value, `0..=3`
+    pub const fn brightness_bits(self) -> u8 {
+        (self.0 & Self::BRIGHTNESS_MASK) >> Self::BRIGHTNESS_SHIFT
+    }
+
+    /// Replace the brightness with the low 2 bits of `bits`, keeping the other bits
+    pub const fn with_brightness_bits(self, bits: u8) -> Self {
+        Self(
+            self.0 & !Self::BRIGHTNESS_MASK
+                | (bits << Self::BRIGHTNESS_SHIFT) & Self::BRIGHTNESS_MASK,
+        )
+    }
+
+    /// The tally brightness
+    pub const fn brightness(self) -> Brightness {
+        match self.brightness_bits() {
+            0b00 => Brightness::Zero,
+            0b01 => Brightness::OneSeventh,
+            0b10 => Brightness::OneHalf,
+            _ => Brightness::Full,
+        }
+    }
+
+    /// Replace the brightness, keeping the other bits
+    pub const fn with_brightness(self, brightness: Brightness) -> Self {
+        self.with_brightness_bits(match brightness {
+            Brightness::Zero => 0b00,
+            Brightness::OneSeventh => 0b01,
+            Brightness::OneHalf => 0b10,
+            Brightness::Full => 0b11,
+        })
+    }
+}
+
+impl BitOr for Control {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// A packet's fields, all decoded at once. See [`TSL31Packet::view`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PacketView<'a> {
+    pub address: u8,
+    pub tally: [bool; 4],
+    pub brightness: Brightness,
+    /// The display text, with padding removed as in [`TSL31Packet::display_data`]
+    pub display: &'a str,
+}
+
+/// A labelled, multi-line rendering of a packet for humans reading logs. See
+/// [`TSL31Packet::summary`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Summary<'a, T: AsRef<[u8]>>(&'a TSL31Packet<T>);
+
+impl<T: AsRef<[u8]>> Display for Summary<'_, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let p = self.0;
+        if p.is_broadcast() {
+            writeln!(f, "address:    {} (broadcast)", p.address())?;
+        } else {
+            writeln!(f, "address:    {}", p.address())?;
+        }
+        for (ch, on) in p.tally_iter() {
+            writeln!(f, "tally {}:    {}", ch + 1, if on { "on" } else { "off" })?;
+        }
+        writeln!(f, "brightness: {}", p.brightness())?;
+        write!(f, "display:    {:?}", p.display_data())
+    }
+}
+
+/// Which logical fields differ between two packets, see [`TSL31Packet::diff`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PacketDiff {
+    pub address: bool,
+    /// Per tally channel
+    pub tally: [bool; 4],
+    pub brightness: bool,
+    pub display: bool,
+}
+
+impl PacketDiff {
+    /// True if no fields differ
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// What [`TSL31Packet::set_display_data_lossy`] had to change to make the text fit
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DisplaySetResult {
+    /// The text was longer than 16 characters, so was cut short
+    pub truncated: bool,
+    /// Some characters weren't printable ascii, so were replaced with `?`
+    pub replaced: bool,
+}
+
+impl DisplaySetResult {
+    /// True if the text was set exactly as given
+    pub fn is_exact(&self) -> bool {
+        !self.truncated && !self.replaced
+    }
+}
+
+/// How strictly packets are validated
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ValidationConfig {
+    /// Allow the display field to be padded with nulls. The spec only allows printable ascii,
+    /// but some senders pad with nulls anyway, so this defaults to true.
+    pub allow_null_padding: bool,
+    /// Require the high bit of the address byte to be set. The spec uses it to mark the start of
+    /// a packet, but some nonconforming senders leave it clear, so it can be turned off. Defaults
+    /// to true.
+    pub require_address_marker: bool,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            allow_null_padding: true,
+            require_address_marker: true,
+        }
+    }
+}
+
+/// The physical width of a display, in characters.
+///
+/// The display field is always 16 bytes on the wire, but some devices only show the first 4 or
+/// 8 characters of it. This lets callers check that a label isn't clipped on the device it's
+/// sent to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DisplayWidth {
+    Four,
+    Eight,
+    /// The full display field
+    #[default]
+    Sixteen,
+}
+
+impl DisplayWidth {
+    /// The number of characters shown
+    pub const fn chars(self) -> usize {
+        match self {
+            Self::Four => 4,
+            Self::Eight => 8,
+            Self::Sixteen => 16,
+        }
+    }
+
+    /// Whether `text`, ignoring trailing spaces, fits on the display
+    pub fn fits(self, text: &str) -> bool {
+        text.trim_end().len() <= self.chars()
+    }
+}
+
+/// How packets are framed on the wire.
+///
+/// The spec defines a packet as exactly 18 bytes, but some senders wrap it in extra bytes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FramingProfile {
+    /// Exactly 18 bytes, as per the spec
+    #[default]
+    Strict,
+    /// Strip any trailing CR (0x0D) and LF (0x0A) bytes before validating. Some text-oriented
+    /// senders terminate every packet with CRLF.
+    TrimLineEndings,
+    /// Use the first 18 bytes, ignoring anything after them. Some senders pad datagrams or
+    /// append extra data.
+    IgnoreTrailing,
+    /// Strip a leading SOH (0x01) sync byte, if there is one. Some implementations send it before
+    /// every packet. A packet always starts with its address byte, which has the high bit set, so
+    /// plain packets are unaffected.
+    LeadingSync,
+}
+
+impl FramingProfile {
+    /// Strip the framing from the given bytes, returning the bytes of the packet itself
+    pub fn unframe<'a>(&self, buf: &'a [u8]) -> &'a [u8] {
+        match self {
+            Self::Strict => buf,
+            Self::TrimLineEndings => {
+                let end = buf
+                    .iter()
+                    .rposition(|b| *b != b'\r' && *b != b'\n')
+                    .map_or(0, |i| i + 1);
+                &buf[..end]
+            }
+            Self::IgnoreTrailing => &buf[..buf.len().min(PACKET_LENGTH_31)],
+            Self::LeadingSync => buf.strip_prefix(&[SOH]).unwrap_or(buf),
+        }
+    }
+}
+
 impl<T> TSL31Packet<T>
 where
     T: AsRef<[u8]>,
 {
     /// Summon a packet from the given bytes without checking it.
-    pub fn new_unchecked(buf: T) -> Self {
+    pub const fn new_unchecked(buf: T) -> Self {
         Self { buf }
     }
     /// Validate the the given bytes are a packet and return it, or an error
     pub fn new_checked(buf: T) -> Result<Self, Error> {
+        Self::new_checked_with(buf, ValidationConfig::default())
+    }
+
+    /// As [`new_checked`](Self::new_checked), with the given validation strictness
+    pub fn new_checked_with(buf: T, config: ValidationConfig) -> Result<Self, Error> {
         let p = Self::new_unchecked(buf);
-        p.validate()?;
+        p.validate_with(config)?;
         Ok(p)
     }
 
-    pub(crate) fn validate(&self) -> Result<(), Error> {
-        if self.buf.as_ref().len() != PACKET_LENGTH_31 {
-            return Err(Error::BadLength {
-                expected: PACKET_LENGTH_31,
-                got: self.buf.as_ref().len(),
-            });
-        }
-        if self.buf.as_ref()[fields::ADDRESS] & 0x80 == 0 {
-            return Err(Error::AddressInvalid);
-        }
-        for (i, b) in self.buf.as_ref()[fields::DISPLAY_DATA].iter().enumerate() {
-            // N.B technically null bytes violates the spec, which clearly states that
-            // only ascii in the range 0x20..=0x7f is valid. However at least one OSS
-            // tally tool pads with null so... here we are
-            if !((0x20..=0x7f).contains(b) || *b == 0) {
-                // Safe to cast to u8 as len will never exceed 18
-                return Err(Error::BadDisplayData { position: i as u8 });
-            }
-        }
-        Ok(())
+    /// As [`new_checked`](Self::new_checked), but accepting packets whose address byte doesn't
+    /// have the high bit set, as sent by some nonconforming devices. The address is read from the
+    /// low 7 bits as usual. See [`ValidationConfig::require_address_marker`]
+    pub fn new_checked_no_addr_marker(buf: T) -> Result<Self, Error> {
+        Self::new_checked_with(
+            buf,
+            ValidationConfig {
+                require_address_marker: false,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Check the packet is valid, as [`new_checked`](Self::new_checked) does. Useful after
+    /// building a packet with [`new_unchecked`](Self::new_unchecked) and the setters:
+    ///
+    /// ```rust
+    /// # use tsl_umd::v3_1::{Error, TSL31Packet};
+    /// let mut p = TSL31Packet::new_unchecked([0u8; 18]);
+    /// assert_eq!(p.validate(), Err(Error::AddressInvalid));
+    /// p.set_address(3).unwrap();
+    /// p.set_tally([true, false, false, false]);
+    /// p.set_display_data("cam 3");
+    /// p.validate()?;
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        self.validate_with(ValidationConfig::default())
+    }
+
+    /// As [`validate`](Self::validate), with the given validation strictness
+    pub fn validate_with(&self, config: ValidationConfig) -> Result<(), Error> {
+        validate_bytes(self.buf.as_ref(), config).map(|_| ())
     }
 
     /// Consumes self, returning the inner bytes
@@ -132,127 +560,829 @@ where
         self.buf
     }
 
+    /// The raw bytes of the packet as space-separated hex, for diagnostics
+    pub fn hex_dump(&self) -> HexDump<'_> {
+        HexDump::new(self.buf.as_ref())
+    }
+
+    /// Format the packet as labelled lines, one per field. The [`Display`] impl is more compact
+    pub fn summary(&self) -> Summary<'_, T> {
+        Summary(self)
+    }
+
+    /// Copy the packet out into a new array. Panics if the buffer isn't exactly 18 bytes, which
+    /// can only happen for packets created with [`new_unchecked`](Self::new_unchecked)
+    pub fn to_bytes(&self) -> [u8; PACKET_LENGTH_31] {
+        self.buf
+            .as_ref()
+            .try_into()
+            .expect("packet buffer must be 18 bytes")
+    }
+
     /// Return the display data as a string, with trailing space/null bytes removed
     pub fn display_data(&self) -> &str {
+        self.display_data_untrimmed().trim_end()
+    }
+
+    /// Return the display data as a string up to the first null byte. Unlike
+    /// [`display_data`](Self::display_data) trailing spaces are kept, for devices where they're
+    /// significant (e.g. for alignment). On a packet that hasn't been validated, this also stops
+    /// at the first byte that isn't valid UTF-8.
+    pub fn display_data_untrimmed(&self) -> &str {
         // Use up to the first null byte, or the whole 16 chars
         let range = self.buf.as_ref()[fields::DISPLAY_DATA]
             .iter()
             .position(|c| *c == 0)
             .map(|e| fields::DISPLAY_DATA.start..e + fields::DISPLAY_DATA.start)
             .unwrap_or(fields::DISPLAY_DATA);
-        // This is checked in `new_checked` so is safe to do
-        unsafe { str::from_utf8_unchecked(&self.buf.as_ref()[range]).trim_end() }
+        valid_prefix(&self.buf.as_ref()[range])
+    }
+
+    /// Iterate over the characters of [`display_data`](Self::display_data), e.g. for drawing glyph
+    /// by glyph. Display data is ascii, so each byte is one character.
+    pub fn display_chars(&self) -> impl Iterator<Item = char> + use<'_, T> {
+        self.display_data().bytes().map(char::from)
+    }
+
+    /// Return the display data as a string, with trailing `pad` bytes removed, for devices that
+    /// pad with something other than space or null (e.g. `0xFF`).
+    ///
+    /// Validation only accepts printable ascii and null padding, so packets padded with other
+    /// non-printable bytes must be created with [`new_unchecked`](Self::new_unchecked). If the
+    /// field still isn't valid UTF-8 after removing the padding, only the text before the first
+    /// invalid byte is returned, as with the other display getters.
+    pub fn display_data_trimmed_with(&self, pad: u8) -> &str {
+        let raw = self.display_data_raw();
+        let end = raw.iter().rposition(|b| *b != pad).map_or(0, |i| i + 1);
+        valid_prefix(&raw[..end])
+    }
+
+    /// How many more characters fit in the display field after the current text, i.e. 16 minus
+    /// the length of [`display_data`](Self::display_data)
+    pub fn display_remaining(&self) -> usize {
+        fields::DISPLAY_DATA.len() - self.display_data().len()
+    }
+
+    /// Whether the display text fits on a display of the given width, i.e. won't be clipped
+    pub fn display_fits(&self, width: DisplayWidth) -> bool {
+        width.fits(self.display_data())
+    }
+
+    /// Return the full 16 byte display field, including any padding
+    pub fn display_data_raw(&self) -> &[u8] {
+        &self.buf.as_ref()[fields::DISPLAY_DATA]
     }
 
-    /// The packet address, from `0x00..=0x7E`
+    /// The packet address, from `0x00..=BROADCAST_ADDRESS`
     pub fn address(&self) -> u8 {
         self.buf.as_ref()[fields::ADDRESS] & 0x7f
     }
 
+    /// True if the packet is addressed to all displays, see [`BROADCAST_ADDRESS`]
+    pub fn is_broadcast(&self) -> bool {
+        self.address() == BROADCAST_ADDRESS
+    }
+
+    /// The raw CONTROL byte.
+    ///
+    /// The typed API manages the lower 6 bits: bits 0-3 are tally channels 1-4 (see
+    /// [`tally`](Self::tally)), bits 4-5 are the brightness (see [`brightness`](Self::brightness)).
+    /// Bit 6 is reserved and bit 7 is always clear in a valid packet - neither is touched by the
+    /// typed API. See [`control`](Self::control) for named access to the bits.
+    pub fn control_byte(&self) -> u8 {
+        self.buf.as_ref()[fields::CONTROL]
+    }
+
+    /// The CONTROL byte as a [`Control`]
+    pub fn control(&self) -> Control {
+        Control::from_byte(self.control_byte())
+    }
+
     /// Tally states, 4 channels
     pub fn tally(&self) -> [bool; 4] {
-        let ctrl = self.buf.as_ref()[fields::CONTROL];
-        [
-            ctrl & 0b1 != 0,
-            ctrl & 0b10 != 0,
-            ctrl & 0b100 != 0,
-            ctrl & 0b1000 != 0,
-        ]
+        self.control().tally().to_array()
+    }
+
+    /// Tally states packed into the low 4 bits, channel 1 in bit 0. See [`Tally`]
+    pub fn tally_mask(&self) -> u8 {
+        self.control().tally().bits()
+    }
+
+    /// The tally state with every channel inverted, e.g. for flashing tally. The packet itself
+    /// isn't changed, see [`toggle_all_tally`](Self::toggle_all_tally)
+    pub fn with_all_tally_toggled(&self) -> [bool; 4] {
+        self.tally().map(|on| !on)
+    }
+
+    /// Iterate over the tally channels, yielding each channel's index (from 0) and state
+    ///
+    /// ```rust
+    /// # use tsl_umd::v3_1::TSL31Packet;
+    /// let mut p = TSL31Packet::default();
+    /// p.set_tally([true, false, false, true]);
+    /// for (ch, on) in p.tally_iter() {
+    ///     println!("channel {}: {}", ch + 1, if on { "on air" } else { "off" });
+    /// }
+    /// assert_eq!(p.tally_iter().filter(|(_, on)| *on).count(), 2);
+    /// ```
+    pub fn tally_iter(&self) -> impl Iterator<Item = (usize, bool)> + use<T> {
+        self.tally().into_iter().enumerate()
+    }
+
+    /// How many of the 4 tally channels are on
+    pub fn on_tally_count(&self) -> u8 {
+        self.tally().iter().filter(|on| **on).count() as u8
+    }
+
+    /// True if any tally channel is on
+    pub fn any_tally_on(&self) -> bool {
+        self.tally().contains(&true)
     }
 
     /// Tally brightness
     pub fn brightness(&self) -> Brightness {
-        match (self.buf.as_ref()[fields::CONTROL] >> 4) & 0x3 {
-            0 => Brightness::Zero,
-            0b01 => Brightness::OneSeventh,
-            0b10 => Brightness::OneHalf,
-            0b11 => Brightness::Full,
-            _ => unreachable!(),
-        }
+        self.control().brightness()
     }
-}
 
-/// The given address was out of range
-#[derive(Debug)]
-pub struct AddressOutOfRangeError;
+    /// The raw 2-bit brightness value (`0..=3`), as encoded in the CONTROL byte
+    pub fn brightness_bits(&self) -> u8 {
+        self.control().brightness_bits()
+    }
 
-impl<T> TSL31Packet<T>
-where
-    T: AsMut<[u8]> + AsRef<[u8]>,
-{
-    /// Set the address. Return Err(()) if the addr is out of range
-    pub fn set_address(&mut self, addr: u8) -> Result<(), AddressOutOfRangeError> {
-        if !(0x0..=0x7E).contains(&addr) {
-            return Err(AddressOutOfRangeError);
-        }
-        self.buf.as_mut()[fields::ADDRESS] = addr + 0x80;
-        Ok(())
+    /// The intensity of each tally channel, from 0.0 to 1.0: 0.0 if the channel is off, or the
+    /// packet's brightness (see [`Brightness`]'s `f32` conversion) if it's on. Ready to use as
+    /// PWM duty cycles for dimmable lamps.
+    pub fn tally_intensities(&self) -> [f32; 4] {
+        let brightness = f32::from(self.brightness());
+        self.tally().map(|on| if on { brightness } else { 0.0 })
     }
 
-    /// Set the tally state
-    pub fn set_tally(&mut self, state: [bool; 4]) {
-        let b: u8 = state
-            .iter()
-            .enumerate()
-            .map(|(i, v)| if *v { 1 << i } else { 0 })
-            .sum();
-        self.buf.as_mut()[fields::CONTROL] = (self.buf.as_ref()[fields::CONTROL] & 0xf0) | b;
+    /// Decode all of the fields at once into a [`PacketView`] borrowing the display text
+    pub fn view(&self) -> PacketView<'_> {
+        PacketView {
+            address: self.address(),
+            tally: self.tally(),
+            brightness: self.brightness(),
+            display: self.display_data(),
+        }
     }
 
-    pub fn set_brightness(&mut self, brightness: Brightness) {
-        let b = match brightness {
-            Brightness::Zero => 0,
-            Brightness::OneSeventh => 0b01 << 4,
-            Brightness::OneHalf => 0b10 << 4,
-            Brightness::Full => 0b11 << 4,
-        };
-        self.buf.as_mut()[fields::CONTROL] = (self.buf.as_ref()[fields::CONTROL] & 0x0f) | b;
+    /// Compare two packets by address, control byte and display text, ignoring differences in
+    /// display padding (e.g. spaces vs nulls). `==` compares the raw bytes.
+    pub fn eq_logical<U: AsRef<[u8]>>(&self, other: &TSL31Packet<U>) -> bool {
+        self.address() == other.address()
+            && self.control_byte() == other.control_byte()
+            && self.display_data() == other.display_data()
     }
 
-    /// Set the display data. Panics if length > 16 or string does not contain printable ascii
-    pub fn set_display_data<'a, S>(&mut self, s: S)
-    where
-        S: Into<&'a str>,
-    {
-        // TODO: don't panic
-        let s: &str = s.into();
-        if s.len() > 16 {
-            panic!("String must not be longer than 16 chars");
+    /// Compare the logical fields of two packets, returning which of them differ. Unlike `==`,
+    /// this ignores differences in display padding.
+    pub fn diff<U: AsRef<[u8]>>(&self, other: &TSL31Packet<U>) -> PacketDiff {
+        let (a, b) = (self.tally(), other.tally());
+        PacketDiff {
+            address: self.address() != other.address(),
+            tally: [a[0] != b[0], a[1] != b[1], a[2] != b[2], a[3] != b[3]],
+            brightness: self.brightness() != other.brightness(),
+            display: self.display_data() != other.display_data(),
         }
-        if !s.as_bytes().iter().all(|c| VALID_DISPLAY.contains(c)) {
-            panic!("String must be printable ascii only");
+    }
+}
+
+/// Check display data bytes are printable ascii, allowing trailing null padding. Returns the
+/// length of the data before any null padding.
+const fn validate_display(bytes: &[u8]) -> Result<usize, Error> {
+    let mut first_null = None;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        // N.B technically null bytes violates the spec, which clearly states that
+        // only ascii in the range 0x20..=0x7f is valid. However at least one OSS
+        // tally tool pads with null so... here we are
+        if b == 0 {
+            if first_null.is_none() {
+                first_null = Some(i);
+            }
+        } else if b < *VALID_DISPLAY.start() || b > *VALID_DISPLAY.end() {
+            // Safe to cast to u8 as len will never exceed 18
+            return Err(Error::BadDisplayData { position: i as u8 });
+        } else if let Some(null) = first_null {
+            // Only allow nulls as padding: `display_data` stops at the first null, so anything
+            // after it would be silently lost
+            return Err(Error::BadDisplayData {
+                position: null as u8,
+            });
         }
-        // Length is checked above, so safe to do this
-        self.buf.as_mut()[fields::DISPLAY_DATA.start..fields::DISPLAY_DATA.start + s.len()]
-            .copy_from_slice(s.as_bytes());
+        i += 1;
+    }
+    match first_null {
+        Some(null) => Ok(null),
+        None => Ok(bytes.len()),
     }
 }
 
-impl<T> Display for TSL31Packet<T>
+/// The longest prefix of the bytes that's valid UTF-8. Packets built with `new_unchecked` may hold
+/// anything in the display field, so the display getters can't assume validation has run
+fn valid_prefix(bytes: &[u8]) -> &str {
+    match str::from_utf8(bytes) {
+        Ok(s) => s,
+        // Safe as `valid_up_to` is the length of the longest valid prefix
+        Err(e) => unsafe { str::from_utf8_unchecked(&bytes[..e.valid_up_to()]) },
+    }
+}
+
+/// Check the bytes are a valid packet under the given config, returning the length of the display
+/// text before any null padding. Shared by everything that validates, so the rules live in one place
+fn validate_bytes(buf: &[u8], config: ValidationConfig) -> Result<usize, Error> {
+    if buf.len() != PACKET_LENGTH_31 {
+        return Err(Error::BadLength {
+            expected: PACKET_LENGTH_31,
+            got: buf.len(),
+        });
+    }
+    if config.require_address_marker && buf[fields::ADDRESS] & 0x80 == 0 {
+        return Err(Error::AddressInvalid);
+    }
+    if buf[fields::CONTROL] & 0x80 != 0 {
+        return Err(Error::ControlInvalid);
+    }
+    let len = validate_display(&buf[fields::DISPLAY_DATA])?;
+    if !config.allow_null_padding && len < fields::DISPLAY_DATA.len() {
+        // Safe to cast to u8 as len will never exceed 18
+        return Err(Error::BadDisplayData {
+            position: len as u8,
+        });
+    }
+    Ok(len)
+}
+
+/// The ADDRESS byte for the given address, with the marker bit set. Every validating address
+/// setter goes through this, so they all accept `0x00..=BROADCAST_ADDRESS` and fail the same way
+const fn address_byte(addr: u8) -> Result<u8, Error> {
+    if addr > BROADCAST_ADDRESS {
+        return Err(Error::AddressOutOfRange { got: addr });
+    }
+    Ok(addr | 0x80)
+}
+
+/// `const` versions of the getters. These are only available for array-backed packets, as
+/// trait methods (i.e. `AsRef`) can't be called in a const context.
+impl TSL31Packet<[u8; PACKET_LENGTH_31]> {
+    /// The packet address, see [`address`](Self::address)
+    pub const fn address_const(&self) -> u8 {
+        self.buf[fields::ADDRESS] & 0x7f
+    }
+
+    /// The raw CONTROL byte, see [`control_byte`](Self::control_byte)
+    pub const fn control_byte_const(&self) -> u8 {
+        self.buf[fields::CONTROL]
+    }
+
+    /// Tally states, see [`tally`](Self::tally)
+    pub const fn tally_const(&self) -> [bool; 4] {
+        Control::from_byte(self.control_byte_const())
+            .tally()
+            .to_array()
+    }
+
+    /// Tally brightness, see [`brightness`](Self::brightness)
+    pub const fn brightness_const(&self) -> Brightness {
+        Control::from_byte(self.control_byte_const()).brightness()
+    }
+}
+
+impl<T> TslPacket for TSL31Packet<T>
 where
     T: AsRef<[u8]>,
 {
-    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(
-            f,
-            "addr={}, 1={}, 2={}, 3={}, 4={}, brightness={}, display={}",
-            self.address(),
-            self.tally()[0],
-            self.tally()[1],
-            self.tally()[2],
-            self.tally()[3],
-            self.brightness(),
-            self.display_data()
-        )
+    const VERSION: Version = Version::V3_1;
+    const LENGTH: usize = PACKET_LENGTH_31;
+    const TALLY_CHANNELS: usize = 4;
+
+    fn display_address(&self) -> u16 {
+        self.address().into()
+    }
+
+    fn display_text(&self) -> &str {
+        self.display_data()
+    }
+
+    fn tally_lit(&self, channel: usize) -> Option<bool> {
+        self.tally().get(channel).copied()
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    const VALID_RAW: [u8; PACKET_LENGTH_31] = [
-        0x80 + 0x69,
-        0b00011001,
+/// Parse a packet from a byte slice, as with [`TSL31Packet::new_checked`]
+///
+/// ```rust
+/// # use tsl_umd::v3_1::{Error, TSL31Packet};
+/// # fn main() -> Result<(), Error> {
+/// let buf: &[u8] = &[0x8d, 0x31, b'C', b'A', b'M', b' ', b'1', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+/// let p: TSL31Packet<_> = buf.try_into()?;
+/// assert_eq!(p.address(), 13);
+/// assert_eq!(p.display_data(), "CAM 1");
+/// # Ok(())
+/// # }
+/// ```
+impl<'a> TryFrom<&'a [u8]> for TSL31Packet<&'a [u8]> {
+    type Error = Error;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::new_checked(buf)
+    }
+}
+
+impl<'a> TSL31Packet<&'a [u8]> {
+    /// Strip the given framing from the bytes, then validate the packet as with
+    /// [`new_checked`](Self::new_checked)
+    pub fn new_checked_framed(buf: &'a [u8], framing: FramingProfile) -> Result<Self, Error> {
+        Self::new_checked(framing.unframe(buf))
+    }
+
+    /// Validate the bytes and decode them straight into a [`TallyState`]. Equivalent to
+    /// [`new_checked`](Self::new_checked) followed by [`decode`](Self::decode).
+    #[cfg(feature = "heapless")]
+    pub fn parse_fields(buf: &[u8]) -> Result<TallyState, Error> {
+        Self::parse_fields_with(buf, ValidationConfig::default())
+    }
+
+    /// As [`parse_fields`](Self::parse_fields), with the given validation strictness
+    #[cfg(feature = "heapless")]
+    pub fn parse_fields_with(buf: &[u8], config: ValidationConfig) -> Result<TallyState, Error> {
+        let len = validate_bytes(buf, config)?;
+        let display = &buf[fields::DISPLAY_DATA];
+        // Validated above, so safe to do
+        let display = unsafe { str::from_utf8_unchecked(&display[..len]) }.trim_end();
+        let ctrl = Control::from_byte(buf[fields::CONTROL]);
+        Ok(TallyState {
+            address: buf[fields::ADDRESS] & 0x7f,
+            tally: ctrl.tally().to_array(),
+            brightness: ctrl.brightness(),
+            // The display field is only 16 bytes, so this always fits
+            display: display.try_into().unwrap(),
+        })
+    }
+
+    /// Parse a packet that may be preceded by a SOH (0x01) sync byte, see
+    /// [`FramingProfile::LeadingSync`]
+    pub fn new_checked_with_sync(buf: &'a [u8]) -> Result<Self, Error> {
+        Self::new_checked_framed(buf, FramingProfile::LeadingSync)
+    }
+
+    /// Parse a packet from the start of a datagram, ignoring any bytes after the first 18. Use
+    /// [`new_checked`](Self::new_checked) to require the datagram is exactly one packet long.
+    pub fn from_datagram(buf: &'a [u8]) -> Result<Self, Error> {
+        Self::new_checked_framed(buf, FramingProfile::IgnoreTrailing)
+    }
+
+    /// Cheaply check whether the bytes could be a packet, looking only at the length and the
+    /// address marker bit. This skips checking the display data, so is useful for quickly
+    /// filtering mixed traffic - [`new_checked`](Self::new_checked) is still needed to know the
+    /// packet is actually valid.
+    pub fn looks_valid(buf: &[u8]) -> bool {
+        Self::check_length(buf) && buf[fields::ADDRESS] & 0x80 != 0
+    }
+
+    /// Whether the bytes are the right length for a packet. The cheapest possible pre-check, for
+    /// rejecting wrong-sized datagrams without going through [`Error`]
+    pub const fn check_length(buf: &[u8]) -> bool {
+        buf.len() == PACKET_LENGTH_31
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T> From<&TSL31Packet<T>> for TSL31Packet<Vec<u8>>
+where
+    T: AsRef<[u8]>,
+{
+    /// Copy the packet into an owned, heap-allocated buffer
+    fn from(p: &TSL31Packet<T>) -> Self {
+        Self::new_unchecked(p.buf.as_ref().to_vec())
+    }
+}
+
+impl<'a> TSL31Packet<&'a mut [u8]> {
+    /// Validate a mutable buffer in place, returning a packet that edits it directly through the
+    /// setters
+    pub fn from_mut_checked(buf: &'a mut [u8]) -> Result<Self, Error> {
+        Self::new_checked(buf)
+    }
+
+    /// Rewrite the address of a finished packet in place, e.g. when forwarding it to another
+    /// display. Only the address byte is written and nothing else is re-validated.
+    pub fn rewrite_address(&mut self, addr: u8) -> Result<(), Error> {
+        self.set_address(addr)
+    }
+}
+
+impl Default for TSL31Packet<[u8; PACKET_LENGTH_31]> {
+    /// A valid, empty packet: address 0, all tally off, full brightness and a blank display
+    fn default() -> Self {
+        let mut p = Self::new_unchecked([b' '; PACKET_LENGTH_31]);
+        p.set_address(0).unwrap();
+        p.set_control_byte(0);
+        p.set_brightness(Brightness::Full);
+        p
+    }
+}
+
+impl TSL31Packet<[u8; PACKET_LENGTH_31]> {
+    /// A packet backed by a fresh, zeroed array. Unlike [`Default`] this is raw: it isn't valid
+    /// until at least the address is set.
+    pub const fn new_zeroed() -> Self {
+        Self::new_unchecked([0; PACKET_LENGTH_31])
+    }
+
+    /// A valid packet for the given address with all tally off, full brightness and a blank
+    /// display, for receivers that need refreshing periodically to not treat silence as a fault.
+    pub fn heartbeat(address: u8) -> Result<Self, Error> {
+        let mut p = Self::default();
+        p.set_address(address)?;
+        Ok(p)
+    }
+
+    /// Build a packet from two tally contacts, the usual way of bridging hardware tally to UMD:
+    /// program (on air) drives channel 1 and preview drives channel 2. The display is blank.
+    pub fn from_gpio(
+        address: u8,
+        pgm: bool,
+        pvw: bool,
+        brightness: Brightness,
+    ) -> Result<Self, Error> {
+        let mut p = Self::heartbeat(address)?;
+        p.set_control([pgm, pvw, false, false], brightness);
+        Ok(p)
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<T> TSL31Packet<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Decode the logical content of the packet
+    pub fn decode(&self) -> TallyState {
+        TallyState {
+            address: self.address(),
+            tally: self.tally(),
+            brightness: self.brightness(),
+            // The display field is only 16 bytes, so this always fits
+            display: self.display_data().try_into().unwrap(),
+        }
+    }
+
+    /// Whether the packet's logical content is the given state, ignoring display padding. The
+    /// same as comparing with [`decode`](Self::decode), without building a new state
+    pub fn matches_state(&self, state: &TallyState) -> bool {
+        self.address() == state.address
+            && self.tally() == state.tally
+            && self.brightness() == state.brightness
+            && self.display_data() == state.display.trim_end()
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl TSL31Packet<[u8; PACKET_LENGTH_31]> {
+    /// Encode the given state into a new packet, with the display space-padded. Built on
+    /// [`apply`](Self::apply), so any decoded state, including a broadcast, can be re-encoded.
+    /// Panics if the address is above [`BROADCAST_ADDRESS`] or the display isn't printable ascii
+    pub fn encode(state: &TallyState) -> Self {
+        let mut p = Self::default();
+        p.apply(state).expect("state must be encodable");
+        p
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<T> TSL31Packet<T>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+{
+    /// Set every field from the given state, with the display space-padded. Unlike
+    /// [`encode`](Self::encode) this returns an error rather than panicking, and leaves the packet
+    /// untouched on error. Any decoded state can be re-applied, including a broadcast.
+    pub fn apply(&mut self, state: &TallyState) -> Result<(), Error> {
+        let address = address_byte(state.address)?;
+        self.set_display_data_raw(state.display.as_bytes())?;
+        self.buf.as_mut()[fields::ADDRESS] = address;
+        self.set_control(state.tally, state.brightness);
+        Ok(())
+    }
+}
+
+impl<T> TSL31Packet<T>
+where
+    T: AsMut<[u8]> + AsRef<[u8]>,
+{
+    /// Set the address, from `0x00..=BROADCAST_ADDRESS`. Returns an error if it's out of range
+    pub fn set_address(&mut self, addr: u8) -> Result<(), Error> {
+        self.buf.as_mut()[fields::ADDRESS] = address_byte(addr)?;
+        Ok(())
+    }
+
+    /// Set the address, clamping it into `0x00..=0x7E` so it never becomes [`BROADCAST_ADDRESS`].
+    /// Returns the address that was set
+    pub fn set_address_clamped(&mut self, addr: u8) -> u8 {
+        let addr = addr.min(0x7E);
+        self.buf.as_mut()[fields::ADDRESS] = addr | 0x80;
+        addr
+    }
+
+    /// Address the packet to all displays, see [`BROADCAST_ADDRESS`]
+    pub fn set_broadcast_address(&mut self) {
+        self.buf.as_mut()[fields::ADDRESS] = BROADCAST_ADDRESS + 0x80;
+    }
+
+    /// Set the raw CONTROL byte, including the bits the typed API doesn't manage. See
+    /// [`control_byte`](Self::control_byte) for the layout.
+    pub fn set_control_byte(&mut self, b: u8) {
+        self.buf.as_mut()[fields::CONTROL] = b;
+    }
+
+    /// Set the tally state
+    pub fn set_tally(&mut self, state: [bool; 4]) {
+        self.set_control_byte(self.control().with_tally(state.into()).to_byte());
+    }
+
+    /// Invert every tally channel in place, keeping the brightness
+    pub fn toggle_all_tally(&mut self) {
+        self.set_tally(self.with_all_tally_toggled());
+    }
+
+    /// Set the tally state from the low 4 bits of `mask`, channel 1 in bit 0. The other bits are
+    /// ignored
+    pub fn set_tally_mask(&mut self, mask: u8) {
+        self.set_control_byte(self.control().with_tally(Tally::from_bits(mask)).to_byte());
+    }
+
+    pub fn set_brightness(&mut self, brightness: Brightness) {
+        self.set_control_byte(self.control().with_brightness(brightness).to_byte());
+    }
+
+    /// Set the raw 2-bit brightness value. Returns an error if `bits` is over 3
+    pub fn set_brightness_bits(&mut self, bits: u8) -> Result<(), Error> {
+        if bits > 0x3 {
+            return Err(Error::BadBrightness { got: bits });
+        }
+        self.set_control_byte(self.control().with_brightness_bits(bits).to_byte());
+        Ok(())
+    }
+
+    /// Set the tally state and brightness together, in a single write of the CONTROL byte
+    pub fn set_control(&mut self, tally: [bool; 4], brightness: Brightness) {
+        self.set_control_byte(
+            self.control()
+                .with_tally(tally.into())
+                .with_brightness(brightness)
+                .to_byte(),
+        );
+    }
+
+    /// Clear the display field to spaces
+    pub fn clear_display(&mut self) {
+        self.buf.as_mut()[fields::DISPLAY_DATA].fill(b' ');
+    }
+
+    /// Set the display data, space-padded. Panics if length > 16 or string does not contain
+    /// printable ascii.
+    ///
+    /// As with validation, trailing null padding is accepted - in which case the rest of the field
+    /// is null-padded too, so a null-padded display field can be set back as it was read.
+    pub fn set_display_data(&mut self, s: impl AsRef<str>) {
+        // TODO: don't panic
+        let s = s.as_ref();
+        if s.len() > 16 {
+            panic!("String must not be longer than 16 chars");
+        }
+        let text = s.trim_end_matches('\0');
+        if !text.as_bytes().iter().all(|c| VALID_DISPLAY.contains(c)) {
+            panic!("String must be printable ascii only");
+        }
+        let pad = if text.len() < s.len() { 0 } else { b' ' };
+        self.buf.as_mut()[fields::DISPLAY_DATA].fill(pad);
+        // Length is checked above, so safe to do this
+        self.buf.as_mut()[fields::DISPLAY_DATA.start..fields::DISPLAY_DATA.start + s.len()]
+            .copy_from_slice(s.as_bytes());
+    }
+
+    /// Set the display text, space-padded, fixing it up rather than failing: characters that
+    /// aren't printable ascii are replaced with `?` and anything over 16 characters is dropped.
+    /// The result reports which of these happened.
+    pub fn set_display_data_lossy(&mut self, s: &str) -> DisplaySetResult {
+        let mut result = DisplaySetResult::default();
+        let mut display = [b' '; PACKET_LENGTH_31 - fields::DISPLAY_DATA.start];
+        for (i, c) in s.chars().enumerate() {
+            if i >= display.len() {
+                result.truncated = true;
+                break;
+            }
+            display[i] = match u8::try_from(c) {
+                Ok(b) if VALID_DISPLAY.contains(&b) => b,
+                _ => {
+                    result.replaced = true;
+                    b'?'
+                }
+            };
+        }
+        self.buf.as_mut()[fields::DISPLAY_DATA].copy_from_slice(&display);
+        result
+    }
+
+    /// Set the display field from an iterator of bytes, space-padded. Each byte must be printable
+    /// ascii. The packet is left unchanged on error.
+    ///
+    /// The iterator is only read one byte past the end of the field, so if it's too long
+    /// [`Error::DisplayTooLong`] reports `got` as 17 rather than the iterator's full length.
+    pub fn set_display_from_iter<I: IntoIterator<Item = u8>>(
+        &mut self,
+        iter: I,
+    ) -> Result<(), Error> {
+        let mut display = [b' '; PACKET_LENGTH_31 - fields::DISPLAY_DATA.start];
+        for (i, b) in iter.into_iter().enumerate() {
+            if i >= display.len() {
+                return Err(Error::DisplayTooLong {
+                    max: display.len(),
+                    got: i + 1,
+                });
+            }
+            if !VALID_DISPLAY.contains(&b) {
+                return Err(Error::BadDisplayData { position: i as u8 });
+            }
+            display[i] = b;
+        }
+        self.buf.as_mut()[fields::DISPLAY_DATA].copy_from_slice(&display);
+        Ok(())
+    }
+
+    /// Set the display field from raw bytes, space-padded. The bytes are checked as validation
+    /// does, so this can re-emit a display field verbatim. As with
+    /// [`set_display_data`](Self::set_display_data), if the bytes end in null padding the rest of
+    /// the field is null-padded too.
+    pub fn set_display_data_raw(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        if bytes.len() > fields::DISPLAY_DATA.len() {
+            return Err(Error::DisplayTooLong {
+                max: fields::DISPLAY_DATA.len(),
+                got: bytes.len(),
+            });
+        }
+        let len = validate_display(bytes)?;
+        let pad = if len < bytes.len() { 0 } else { b' ' };
+        self.buf.as_mut()[fields::DISPLAY_DATA].fill(pad);
+        self.buf.as_mut()[fields::DISPLAY_DATA.start..fields::DISPLAY_DATA.start + bytes.len()]
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Normalize the padding of the display field.
+    ///
+    /// Senders disagree on padding - some use spaces, some nulls, and some mix the two, which can
+    /// leave nulls in the middle of the text. The rule applied here is: everything up to the last
+    /// printable (non-space, non-null) character is content and is preserved, with any nulls
+    /// within it replaced by spaces. Everything after it is padding, and is filled with spaces.
+    pub fn normalize_padding(&mut self) {
+        for b in self.buf.as_mut()[fields::DISPLAY_DATA].iter_mut() {
+            if *b == 0 {
+                *b = b' ';
+            }
+        }
+    }
+
+    /// Rewrite the packet into a canonical form, so that valid packets which are
+    /// [`eq_logical`](Self::eq_logical) are also byte-identical and hash the same. For v3.1 the
+    /// only difference between such packets is display padding, so this currently just calls
+    /// [`normalize_padding`](Self::normalize_padding).
+    pub fn normalize(&mut self) {
+        self.normalize_padding();
+    }
+}
+
+impl<T> Display for TSL31Packet<T>
+where
+    T: AsRef<[u8]>,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "addr={}, 1={}, 2={}, 3={}, 4={}, brightness={}, display={}",
+            self.address(),
+            self.tally()[0],
+            self.tally()[1],
+            self.tally()[2],
+            self.tally()[3],
+            self.brightness(),
+            self.display_data()
+        )
+    }
+}
+
+/// Decodes packets from an unframed byte stream, e.g. a serial or TCP link, where packets are
+/// simply sent back to back.
+///
+/// If bytes are lost or garbage is received the decoder resyncs: the address byte is the only
+/// byte of a valid packet with its high bit set, so anything before such a byte is skipped, and
+/// if the 18 bytes from there don't validate, the search starts again from the next one.
+///
+/// ```rust
+/// # use tsl_umd::v3_1::{StreamDecoder, TSL31Packet};
+/// let packet = TSL31Packet::default();
+/// let mut decoder = StreamDecoder::new();
+/// // Garbage, then a packet split across two reads
+/// assert_eq!(decoder.next_packet(&mut &b"\x01\x02"[..]), None);
+/// let bytes = packet.to_bytes();
+/// let mut input = &bytes[..10];
+/// assert_eq!(decoder.next_packet(&mut input), None);
+/// let mut input = &bytes[10..];
+/// assert_eq!(decoder.next_packet(&mut input), Some(packet));
+/// assert!(input.is_empty());
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct StreamDecoder {
+    buf: [u8; PACKET_LENGTH_31],
+    len: usize,
+}
+
+impl StreamDecoder {
+    /// Create a decoder with nothing buffered
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; PACKET_LENGTH_31],
+            len: 0,
+        }
+    }
+
+    /// The number of bytes buffered towards the next packet
+    pub fn buffered(&self) -> usize {
+        self.len
+    }
+
+    /// Drop any buffered bytes, e.g. after reconnecting
+    pub fn reset(&mut self) {
+        self.len = 0;
+    }
+
+    /// Feed bytes from the front of `input` into the decoder until a complete, valid packet is
+    /// found, advancing `input` past the bytes consumed. Bytes after the packet are left in
+    /// `input` for the next call, so nothing is lost by stopping early. Returns `None` once
+    /// `input` is used up without finishing a packet; the partial packet stays buffered.
+    ///
+    /// ```rust
+    /// # use tsl_umd::v3_1::StreamDecoder;
+    /// # let bytes = [];
+    /// let mut decoder = StreamDecoder::new();
+    /// let mut input = &bytes[..];
+    /// while let Some(packet) = decoder.next_packet(&mut input) {
+    ///     println!("{packet}");
+    /// }
+    /// ```
+    pub fn next_packet(
+        &mut self,
+        input: &mut &[u8],
+    ) -> Option<TSL31Packet<[u8; PACKET_LENGTH_31]>> {
+        while let Some((b, rest)) = input.split_first() {
+            *input = rest;
+            if let Some(packet) = self.push_byte(*b) {
+                return Some(packet);
+            }
+        }
+        None
+    }
+
+    fn push_byte(&mut self, b: u8) -> Option<TSL31Packet<[u8; PACKET_LENGTH_31]>> {
+        // Wait for something that looks like an address byte to start a packet
+        if self.len == 0 && b & 0x80 == 0 {
+            return None;
+        }
+        self.buf[self.len] = b;
+        self.len += 1;
+        if self.len < PACKET_LENGTH_31 {
+            return None;
+        }
+        if TSL31Packet::new_checked(&self.buf[..]).is_ok() {
+            self.len = 0;
+            return Some(TSL31Packet::new_unchecked(self.buf));
+        }
+        // Not a packet, so resync from the next possible address byte
+        match self.buf[1..].iter().position(|b| b & 0x80 != 0) {
+            Some(i) => {
+                self.buf.copy_within(i + 1.., 0);
+                self.len -= i + 1;
+            }
+            None => self.len = 0,
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::packet::AirState;
+    use proptest::prelude::*;
+    const VALID_RAW: [u8; PACKET_LENGTH_31] = [
+        0x80 + 0x69,
+        0b00011001,
         b'h',
         b'e',
         b'l',
@@ -308,6 +1438,16 @@ mod tests {
         );
     }
 
+    #[test]
+    fn error_control_high_bit() {
+        let mut bad_raw = VALID_RAW;
+        bad_raw[1] = 0xB1;
+        assert_eq!(
+            TSL31Packet::new_checked(bad_raw),
+            Err(Error::ControlInvalid)
+        );
+    }
+
     #[test]
     fn error_bad_display() {
         let mut bad_raw = VALID_RAW;
@@ -320,52 +1460,1226 @@ mod tests {
     }
 
     #[test]
-    fn test_set_address() {
-        let buf = [0u8; PACKET_LENGTH_31];
-        let mut p = TSL31Packet::new_unchecked(buf);
-        p.set_address(42).unwrap();
-        assert_eq!(p.address(), 42);
-        assert!(p.set_address(234).is_err());
+    fn test_framing_trim_line_endings() {
+        let mut raw = [0u8; PACKET_LENGTH_31 + 2];
+        raw[..PACKET_LENGTH_31].copy_from_slice(&VALID_RAW);
+        raw[PACKET_LENGTH_31..].copy_from_slice(b"\r\n");
+        assert_eq!(
+            TSL31Packet::new_checked_framed(&raw, FramingProfile::Strict),
+            Err(Error::BadLength {
+                expected: PACKET_LENGTH_31,
+                got: 20
+            })
+        );
+        let p = TSL31Packet::new_checked_framed(&raw, FramingProfile::TrimLineEndings).unwrap();
+        assert_eq!(p.inner(), &VALID_RAW);
+        // Plain packets are unaffected
+        let p = TSL31Packet::new_checked_framed(&VALID_RAW, FramingProfile::TrimLineEndings);
+        assert!(p.is_ok());
     }
 
     #[test]
-    fn test_set_tally() {
-        let buf = [0u8; PACKET_LENGTH_31];
-        let mut p = TSL31Packet::new_unchecked(buf);
-        for perm in [
-            [false, false, false, false],
-            [true, false, false, false],
-            [false, true, false, false],
-            [false, false, true, false],
-            [false, false, false, true],
-        ] {
-            p.set_tally(perm);
-            assert_eq!(p.tally(), perm);
-        }
+    fn test_framing_leading_sync() {
+        let mut raw = [SOH; PACKET_LENGTH_31 + 1];
+        raw[1..].copy_from_slice(&VALID_RAW);
+        assert_eq!(
+            TSL31Packet::new_checked(&raw[..]),
+            Err(Error::BadLength {
+                expected: PACKET_LENGTH_31,
+                got: 19
+            })
+        );
+        let p = TSL31Packet::new_checked_with_sync(&raw).unwrap();
+        assert_eq!(p.inner(), &VALID_RAW);
+        // Plain packets are unaffected
+        let p = TSL31Packet::new_checked_with_sync(&VALID_RAW).unwrap();
+        assert_eq!(p.inner(), &VALID_RAW);
+        // Only one sync byte is stripped
+        let mut raw = [SOH; PACKET_LENGTH_31 + 2];
+        raw[2..].copy_from_slice(&VALID_RAW);
+        assert!(TSL31Packet::new_checked_with_sync(&raw).is_err());
     }
 
     #[test]
-    fn test_set_brightness() {
-        let buf = [0u8; PACKET_LENGTH_31];
-        let mut p = TSL31Packet::new_unchecked(buf);
-        for b in [
-            Brightness::Zero,
-            Brightness::OneSeventh,
-            Brightness::OneHalf,
-            Brightness::Full,
-        ] {
-            p.set_brightness(b);
-            assert_eq!(p.brightness(), b);
+    fn test_default() {
+        let p = TSL31Packet::default();
+        assert_eq!(p.address(), 0);
+        assert_eq!(p.tally(), [false; 4]);
+        assert_eq!(p.brightness(), Brightness::Full);
+        assert_eq!(p.display_data(), "");
+        assert!(TSL31Packet::new_checked(p.inner()).is_ok());
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        use std::string::ToString;
+
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(
+            p.hex_dump().to_string(),
+            "e9 19 68 65 6c 6c 6f 20 20 20 20 20 20 20 20 20 20 20"
+        );
+        assert_eq!(HexDump::new(&[]).to_string(), "");
+    }
+
+    #[test]
+    fn test_to_bytes() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(p.to_bytes(), VALID_RAW);
+        let p = TSL31Packet::new_checked(&VALID_RAW[..]).unwrap();
+        assert_eq!(p.to_bytes(), VALID_RAW);
+    }
+
+    #[test]
+    fn test_hash() {
+        use std::collections::HashSet;
+
+        let mut seen = HashSet::new();
+        assert!(seen.insert(TSL31Packet::new_checked(VALID_RAW).unwrap()));
+        assert!(!seen.insert(TSL31Packet::new_checked(VALID_RAW).unwrap()));
+        assert!(seen.insert(TSL31Packet::default()));
+    }
+
+    #[test]
+    fn error_interior_null() {
+        let mut bad_raw = VALID_RAW;
+        bad_raw[fields::DISPLAY_DATA.start + 1] = 0;
+        assert_eq!(
+            TSL31Packet::new_checked(bad_raw),
+            Err(Error::BadDisplayData { position: 1 })
+        );
+        // Trailing nulls are fine
+        bad_raw[fields::DISPLAY_DATA.start + 1..].fill(0);
+        assert!(TSL31Packet::new_checked(bad_raw).is_ok());
+    }
+
+    #[test]
+    fn error_text_after_null() {
+        let mut bad_raw = VALID_RAW;
+        bad_raw[fields::DISPLAY_DATA].copy_from_slice(b"hi\0there\0\0\0\0\0\0\0\0");
+        assert_eq!(
+            TSL31Packet::new_checked(bad_raw),
+            Err(Error::BadDisplayData { position: 2 })
+        );
+    }
+
+    #[test]
+    fn test_tsl_packet() {
+        const _: () = assert!(TSL31Packet::<&[u8]>::LENGTH == PACKET_LENGTH_31);
+        assert_eq!(TSL31Packet::<[u8; 18]>::VERSION, Version::V3_1);
+
+        fn lit_channels<P: TslPacket>(p: &P) -> usize {
+            (0..P::TALLY_CHANNELS)
+                .filter(|c| p.tally_lit(*c).unwrap())
+                .count()
         }
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(p.display_address(), 0x69);
+        assert_eq!(p.display_text(), "hello");
+        assert_eq!(lit_channels(&p), 2);
+        assert_eq!(p.tally_lit(3), Some(true));
+        assert_eq!(p.tally_lit(4), None);
     }
 
     #[test]
-    fn test_set_display_data() {
-        let buf = [0u8; PACKET_LENGTH_31];
+    fn test_air_state() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(p.air_state(0), Some(AirState::OnAir));
+        assert_eq!(p.air_state(1), Some(AirState::Off));
+        assert_eq!(p.air_state(2), Some(AirState::Off));
+        assert_eq!(p.air_state(3), Some(AirState::OnAir));
+        assert_eq!(p.air_state(4), None);
+        assert_eq!(p.air_state(usize::MAX), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vec_packet() {
+        use std::net::UdpSocket;
+
+        let owned: TSL31Packet<Vec<u8>> = {
+            let buf = VALID_RAW;
+            let p = TSL31Packet::new_checked(&buf[..]).unwrap();
+            (&p).into()
+        };
+        assert_eq!(owned.display_data(), "hello");
+
+        let rx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let tx = UdpSocket::bind("127.0.0.1:0").unwrap();
+        tx.send_to(&owned.inner(), rx.local_addr().unwrap())
+            .unwrap();
+        let mut buf = [0u8; 64];
+        let (count, _) = rx.recv_from(&mut buf).unwrap();
+        assert_eq!(&buf[..count], &VALID_RAW);
+    }
+
+    #[test]
+    fn test_from_mut_checked() {
+        let mut buf = VALID_RAW;
+        let mut p = TSL31Packet::from_mut_checked(&mut buf[..]).unwrap();
+        p.set_address(3).unwrap();
+        p.set_display_data("edited");
+        assert_eq!(buf[fields::ADDRESS], 0x83);
+        assert_eq!(&buf[fields::DISPLAY_DATA], b"edited          ");
+
+        let mut bad = [0u8; PACKET_LENGTH_31];
+        assert_eq!(
+            TSL31Packet::from_mut_checked(&mut bad[..]),
+            Err(Error::AddressInvalid)
+        );
+    }
+
+    #[test]
+    fn test_rewrite_address() {
+        let mut buf = VALID_RAW;
+        let mut p = TSL31Packet::from_mut_checked(&mut buf[..]).unwrap();
+        p.rewrite_address(0x12).unwrap();
+        assert_eq!(
+            p.rewrite_address(0x80),
+            Err(Error::AddressOutOfRange { got: 0x80 })
+        );
+        assert_eq!(buf[fields::ADDRESS], 0x92);
+        assert_eq!(buf[1..], VALID_RAW[1..]);
+
+        let mut p = TSL31Packet::from_mut_checked(&mut buf[..]).unwrap();
+        p.rewrite_address(BROADCAST_ADDRESS).unwrap();
+        assert!(p.is_broadcast());
+    }
+
+    #[test]
+    fn test_looks_valid() {
+        assert!(TSL31Packet::looks_valid(&VALID_RAW));
+        assert!(!TSL31Packet::looks_valid(&VALID_RAW[1..]));
+        assert!(!TSL31Packet::looks_valid(&[]));
+        assert!(!TSL31Packet::looks_valid(&[0; PACKET_LENGTH_31]));
+        // The display data isn't checked
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start] = 0xff;
+        assert!(TSL31Packet::looks_valid(&raw));
+        assert!(TSL31Packet::new_checked(raw).is_err());
+    }
+
+    #[test]
+    fn test_check_length() {
+        assert!(TSL31Packet::check_length(&VALID_RAW));
+        assert!(TSL31Packet::check_length(&[0; PACKET_LENGTH_31]));
+        assert!(!TSL31Packet::check_length(&VALID_RAW[1..]));
+        assert!(!TSL31Packet::check_length(&[0; PACKET_LENGTH_31 + 1]));
+        assert!(!TSL31Packet::check_length(&[]));
+    }
+
+    #[test]
+    fn test_from_datagram() {
+        let mut datagram = [0u8; 64];
+        datagram[..PACKET_LENGTH_31].copy_from_slice(&VALID_RAW);
+        let p = TSL31Packet::from_datagram(&datagram).unwrap();
+        assert_eq!(p.inner(), &VALID_RAW);
+        assert!(TSL31Packet::from_datagram(&VALID_RAW).is_ok());
+        assert_eq!(
+            TSL31Packet::from_datagram(&VALID_RAW[..10]),
+            Err(Error::BadLength {
+                expected: PACKET_LENGTH_31,
+                got: 10
+            })
+        );
+        assert!(TSL31Packet::new_checked_framed(&datagram, FramingProfile::Strict).is_err());
+    }
+
+    #[test]
+    fn test_validation_null_padding() {
+        let strict = ValidationConfig {
+            allow_null_padding: false,
+            ..Default::default()
+        };
+        let mut raw = VALID_RAW;
+        assert!(TSL31Packet::new_checked_with(raw, strict).is_ok());
+        raw[fields::DISPLAY_DATA.start + 5..].fill(0);
+        assert_eq!(
+            TSL31Packet::new_checked_with(raw, strict),
+            Err(Error::BadDisplayData { position: 5 })
+        );
+        assert!(TSL31Packet::new_checked_with(raw, ValidationConfig::default()).is_ok());
+        assert!(TSL31Packet::new_checked(raw).is_ok());
+    }
+
+    #[test]
+    fn test_no_addr_marker() {
+        let mut raw = VALID_RAW;
+        raw[fields::ADDRESS] &= 0x7f;
+        assert_eq!(TSL31Packet::new_checked(raw), Err(Error::AddressInvalid));
+        let p = TSL31Packet::new_checked_no_addr_marker(raw).unwrap();
+        assert_eq!(p.address(), 0x69);
+        assert_eq!(p.display_data(), "hello");
+        // Conforming packets are still accepted, and the rest is still validated
+        assert!(TSL31Packet::new_checked_no_addr_marker(VALID_RAW).is_ok());
+        raw[fields::DISPLAY_DATA.start] = 0x01;
+        assert_eq!(
+            TSL31Packet::new_checked_no_addr_marker(raw),
+            Err(Error::BadDisplayData { position: 0 })
+        );
+    }
+
+    #[test]
+    fn test_set_address() {
+        let buf = [0u8; PACKET_LENGTH_31];
+        let mut p = TSL31Packet::new_unchecked(buf);
+        p.set_address(42).unwrap();
+        assert_eq!(p.address(), 42);
+        assert_eq!(
+            p.set_address(234),
+            Err(Error::AddressOutOfRange { got: 234 })
+        );
+        assert_eq!(p.address(), 42);
+    }
+
+    #[test]
+    fn test_broadcast_address() {
+        let mut p = TSL31Packet::default();
+        assert!(!p.is_broadcast());
+        p.set_address(BROADCAST_ADDRESS).unwrap();
+        assert!(p.is_broadcast());
+        p.set_address(0).unwrap();
+        p.set_broadcast_address();
+        assert!(p.is_broadcast());
+        assert_eq!(p.address(), BROADCAST_ADDRESS);
+        assert!(TSL31Packet::new_checked(p.inner()).unwrap().is_broadcast());
+    }
+
+    #[test]
+    fn test_tally_bits() {
+        for bits in 0..=0x0f {
+            let state = [bits & 1 != 0, bits & 2 != 0, bits & 4 != 0, bits & 8 != 0];
+            let tally = Tally::from(state);
+            assert_eq!(tally.bits(), bits);
+            assert_eq!(<[bool; 4]>::from(tally), state);
+            assert_eq!(Tally::from_bits(bits), tally);
+            assert_eq!(Tally::from_bits(bits | 0xf0), tally);
+        }
+    }
+
+    #[test]
+    fn test_set_tally() {
+        let buf = [0u8; PACKET_LENGTH_31];
+        let mut p = TSL31Packet::new_unchecked(buf);
+        for perm in [
+            [false, false, false, false],
+            [true, false, false, false],
+            [false, true, false, false],
+            [false, false, true, false],
+            [false, false, false, true],
+        ] {
+            p.set_tally(perm);
+            assert_eq!(p.tally(), perm);
+        }
+    }
+
+    #[test]
+    fn test_on_tally_count() {
+        let mut p = TSL31Packet::default();
+        for (state, count) in [
+            ([false, false, false, false], 0),
+            ([false, false, true, false], 1),
+            ([true, true, true, true], 4),
+        ] {
+            p.set_tally(state);
+            assert_eq!(p.on_tally_count(), count);
+            assert_eq!(p.any_tally_on(), count > 0);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn error_into_io_error() {
+        use std::string::ToString;
+        let e: std::io::Error = Error::AddressInvalid.into();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+        assert_eq!(e.to_string(), "AddressInvalid");
+    }
+
+    #[test]
+    fn test_display_data_trimmed_with() {
+        let mut raw = VALID_RAW;
+        raw[7..].fill(0xff);
+        let p = TSL31Packet::new_unchecked(raw);
+        assert_eq!(p.display_data_trimmed_with(0xff), "hello");
+        assert_eq!(p.display_data_trimmed_with(b' '), "hello");
+        // The other getters stop at the padding rather than reading it as text
+        assert_eq!(p.display_data(), "hello");
+        assert_eq!(p.display_data_untrimmed(), "hello");
+
+        let p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(p.display_data_trimmed_with(b' '), "hello");
+        assert_eq!(p.display_data_trimmed_with(0xff), "hello           ");
+    }
+
+    #[test]
+    fn test_set_display_data_lossy() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        let result = p.set_display_data_lossy("cam 1");
+        assert!(result.is_exact());
+        assert_eq!(p.display_data_untrimmed(), "cam 1           ");
+
+        let result = p.set_display_data_lossy("a much longer label");
+        assert_eq!(
+            result,
+            DisplaySetResult {
+                truncated: true,
+                replaced: false
+            }
+        );
+        assert_eq!(p.display_data(), "a much longer la");
+
+        let result = p.set_display_data_lossy("café\t1");
+        assert_eq!(
+            result,
+            DisplaySetResult {
+                truncated: false,
+                replaced: true
+            }
+        );
+        assert_eq!(p.display_data(), "caf??1");
+
+        // Exactly 16 multi-byte characters fit
+        let result = p.set_display_data_lossy("éééééééééééééééé");
+        assert!(!result.truncated);
+        assert_eq!(p.display_data(), "????????????????");
+        assert!(p.validate().is_ok());
+    }
+
+    #[test]
+    fn test_set_display_from_iter() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        p.set_display_from_iter(b"cam 3".iter().copied()).unwrap();
+        assert_eq!(p.display_data_untrimmed(), "cam 3           ");
+
+        assert_eq!(
+            p.set_display_from_iter(core::iter::repeat_n(b'x', 20)),
+            Err(Error::DisplayTooLong { max: 16, got: 17 })
+        );
+        assert_eq!(
+            p.set_display_from_iter([b'a', 0x01]),
+            Err(Error::BadDisplayData { position: 1 })
+        );
+        assert_eq!(p.display_data(), "cam 3");
+    }
+
+    #[test]
+    fn test_set_address_clamped() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(p.set_address_clamped(0x00), 0x00);
+        assert_eq!(p.address(), 0x00);
+        assert_eq!(p.set_address_clamped(0x7E), 0x7E);
+        assert_eq!(p.address(), 0x7E);
+        for addr in [BROADCAST_ADDRESS, 0x80, 0xFF] {
+            assert_eq!(p.set_address_clamped(addr), 0x7E);
+            assert_eq!(p.address(), 0x7E);
+            assert!(!p.is_broadcast());
+        }
+    }
+
+    #[test]
+    fn test_brightness_bits() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        for (bits, b) in Brightness::all().into_iter().enumerate() {
+            p.set_brightness(b);
+            assert_eq!(p.brightness_bits(), bits as u8);
+            p.set_brightness_bits(bits as u8).unwrap();
+            assert_eq!(p.brightness(), b);
+        }
+        assert_eq!(
+            p.set_brightness_bits(4),
+            Err(Error::BadBrightness { got: 4 })
+        );
+        assert_eq!(p.tally(), [true, false, false, true]);
+    }
+
+    #[test]
+    fn test_set_control() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        p.set_control([false, true, true, false], Brightness::OneHalf);
+        assert_eq!(p.control_byte(), 0b00100110);
+        p.set_control_byte(0b01000000);
+        p.set_control([true, false, false, false], Brightness::Full);
+        assert_eq!(p.control_byte(), 0b01110001);
+    }
+
+    #[test]
+    fn test_set_brightness() {
+        let buf = [0u8; PACKET_LENGTH_31];
+        let mut p = TSL31Packet::new_unchecked(buf);
+        for b in Brightness::all() {
+            p.set_brightness(b);
+            assert_eq!(p.brightness(), b);
+        }
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_decode_encode() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        let state = p.decode();
+        assert_eq!(
+            state,
+            TallyState {
+                address: 0x69,
+                tally: [true, false, false, true],
+                brightness: Brightness::OneSeventh,
+                display: "hello".try_into().unwrap(),
+            }
+        );
+        let encoded = TSL31Packet::encode(&state);
+        assert_eq!(encoded, p);
+        assert_eq!(encoded.decode(), state);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_decode_encode_broadcast() {
+        let mut p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        p.set_broadcast_address();
+        let state = p.decode();
+        assert_eq!(state.address, BROADCAST_ADDRESS);
+        let encoded = TSL31Packet::encode(&state);
+        assert_eq!(encoded, p);
+        assert_eq!(encoded.decode(), state);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_heapless_vec_packet() {
+        let mut buf = heapless::Vec::<u8, 64>::new();
+        buf.extend_from_slice(&VALID_RAW).unwrap();
+        let mut p = TSL31Packet::new_checked(buf).unwrap();
+        assert_eq!(p.address(), 0x69);
+        assert_eq!(p.tally(), [true, false, false, true]);
+        assert_eq!(p.display_data(), "hello");
+        p.set_display_data("edited");
+        assert_eq!(p.display_data(), "edited");
+
+        let mut short = heapless::Vec::<u8, 64>::new();
+        short.extend_from_slice(&VALID_RAW[..10]).unwrap();
+        assert_eq!(
+            TSL31Packet::new_checked(short),
+            Err(Error::BadLength {
+                expected: PACKET_LENGTH_31,
+                got: 10
+            })
+        );
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_matches_state() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        let mut state = p.decode();
+        assert!(p.matches_state(&state));
+
+        // Only the padding differs
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start + 5..].fill(0);
+        assert!(TSL31Packet::new_checked(raw).unwrap().matches_state(&state));
+
+        state.brightness = Brightness::Full;
+        assert!(!p.matches_state(&state));
+        state = p.decode();
+        state.display = "hello!".try_into().unwrap();
+        assert!(!p.matches_state(&state));
+        state = p.decode();
+        state.tally[1] = true;
+        assert!(!p.matches_state(&state));
+        state = p.decode();
+        state.address = 0x6a;
+        assert!(!p.matches_state(&state));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_apply() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        let mut state = p.decode();
+        state.address = 0x12;
+        state.tally = [false, true, false, false];
+        state.brightness = Brightness::Full;
+        state.display = "cam 2".try_into().unwrap();
+        p.apply(&state).unwrap();
+        assert_eq!(p.decode(), state);
+        assert_eq!(p, TSL31Packet::encode(&state));
+
+        state.address = BROADCAST_ADDRESS;
+        p.apply(&state).unwrap();
+        assert!(p.is_broadcast());
+
+        let before = p.to_bytes();
+        state.address = 0x80;
+        assert_eq!(p.apply(&state), Err(Error::AddressOutOfRange { got: 0x80 }));
+        state.address = 0x12;
+        state.display = "tab\t".try_into().unwrap();
+        assert_eq!(p.apply(&state), Err(Error::BadDisplayData { position: 3 }));
+        assert_eq!(p.to_bytes(), before);
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_state_to_packet() {
+        let state = TallyState {
+            address: 0x12,
+            tally: [false, true, true, false],
+            brightness: Brightness::OneHalf,
+            display: "cam 2".try_into().unwrap(),
+        };
+        let p = state.to_packet().unwrap();
+        assert!(p.validate().is_ok());
+        assert_eq!(p.display_data_raw(), b"cam 2           ");
+        assert_eq!(p.decode(), state);
+
+        let bad = TallyState {
+            address: 0xff,
+            ..state
+        };
+        assert_eq!(bad.to_packet(), Err(Error::AddressOutOfRange { got: 0xff }));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_parse_fields() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(TSL31Packet::parse_fields(&VALID_RAW), Ok(p.decode()));
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start + 5..].fill(0);
+        assert_eq!(TSL31Packet::parse_fields(&raw), Ok(p.decode()));
+
+        assert_eq!(
+            TSL31Packet::parse_fields(&VALID_RAW[1..]),
+            Err(Error::BadLength {
+                expected: PACKET_LENGTH_31,
+                got: 17
+            })
+        );
+        let strict = ValidationConfig {
+            allow_null_padding: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            TSL31Packet::parse_fields_with(&raw, strict),
+            Err(Error::BadDisplayData { position: 5 })
+        );
+        raw[fields::ADDRESS] = 0;
+        assert_eq!(TSL31Packet::parse_fields(&raw), Err(Error::AddressInvalid));
+        let lenient = ValidationConfig {
+            require_address_marker: false,
+            ..Default::default()
+        };
+        assert!(TSL31Packet::parse_fields_with(&raw, lenient).is_ok());
+        raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start] = 0x07;
+        assert_eq!(
+            TSL31Packet::parse_fields(&raw),
+            Err(Error::BadDisplayData { position: 0 })
+        );
+    }
+
+    #[test]
+    fn test_control_byte() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(p.control_byte(), 0b00011001);
+        // Reserved bit 6 survives the typed setters
+        p.set_control_byte(0b01000000);
+        p.set_tally([true, true, false, false]);
+        p.set_brightness(Brightness::Full);
+        assert_eq!(p.control_byte(), 0b01110011);
+        assert_eq!(p.tally(), [true, true, false, false]);
+        assert_eq!(p.brightness(), Brightness::Full);
+    }
+
+    #[test]
+    fn test_set_display_data_raw() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        let null_padded = b"CAM 1\0\0\0\0\0\0\0\0\0\0\0";
+        p.set_display_data_raw(null_padded).unwrap();
+        assert_eq!(p.display_data_raw(), null_padded);
+        assert_eq!(p.display_data(), "CAM 1");
+
+        assert!(p.validate().is_ok());
+
+        p.set_display_data_raw(b"hi\0").unwrap();
+        assert_eq!(p.display_data_raw(), b"hi\0\0\0\0\0\0\0\0\0\0\0\0\0\0");
+        assert!(p.validate().is_ok());
+
+        p.set_display_data_raw(b"hi").unwrap();
+        assert_eq!(p.display_data_raw(), b"hi              ");
+        assert!(p.validate().is_ok());
+
+        assert_eq!(
+            p.set_display_data_raw(b"a\x07"),
+            Err(Error::BadDisplayData { position: 1 })
+        );
+        assert_eq!(
+            p.set_display_data_raw(b"a\0b"),
+            Err(Error::BadDisplayData { position: 1 })
+        );
+        assert_eq!(
+            p.set_display_data_raw(&[b'a'; 17]),
+            Err(Error::DisplayTooLong { max: 16, got: 17 })
+        );
+        // Errors leave the field untouched
+        assert_eq!(p.display_data(), "hi");
+    }
+
+    #[test]
+    fn test_normalize_padding() {
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA].copy_from_slice(b"ab\0cd e\0\0  \0\0\0\0\0");
+        let mut p = TSL31Packet::new_unchecked(raw);
+        assert_eq!(p.display_data(), "ab");
+        p.normalize_padding();
+        assert_eq!(p.display_data(), "ab cd e");
+        assert_eq!(&p.inner()[fields::DISPLAY_DATA], b"ab cd e         ");
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA].copy_from_slice(b"hello\0\0\0\0\0\0\0\0\0\0\0");
+        let mut nulls = TSL31Packet::new_checked(raw).unwrap();
+        let mut spaces = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert!(nulls.eq_logical(&spaces));
+        assert_ne!(nulls, spaces);
+        nulls.normalize();
+        spaces.normalize();
+        assert_eq!(nulls, spaces);
+        assert_eq!(nulls.inner(), VALID_RAW);
+    }
+
+    #[cfg(feature = "defmt")]
+    #[test]
+    fn test_defmt_format() {
+        fn assert_format<T: defmt::Format>() {}
+        assert_format::<TSL31Packet<[u8; 18]>>();
+        assert_format::<TSL31Packet<&[u8]>>();
+        assert_format::<Brightness>();
+        assert_format::<Error>();
+        assert_format::<Tally>();
+        assert_format::<Control>();
+        assert_format::<PacketView>();
+        assert_format::<PacketDiff>();
+        assert_format::<ValidationConfig>();
+        assert_format::<FramingProfile>();
+        assert_format::<DisplayWidth>();
+        assert_format::<DisplaySetResult>();
+        assert_format::<StreamDecoder>();
+        assert_format::<crate::hex::HexDump>();
+        assert_format::<crate::packet::Version>();
+        assert_format::<AirState>();
+        #[cfg(feature = "heapless")]
+        assert_format::<crate::state::TallyState>();
+    }
+
+    #[test]
+    fn test_display_chars() {
+        use std::string::String;
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(p.display_chars().collect::<String>(), p.display_data());
+        assert_eq!(p.display_chars().count(), 5);
+        p.set_display_data_raw(b"a b\0").unwrap();
+        assert!(p.validate().is_ok());
+        assert_eq!(p.display_chars().collect::<String>(), "a b");
+        p.clear_display();
+        assert_eq!(p.display_chars().next(), None);
+    }
+
+    #[test]
+    fn test_display_remaining() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(p.display_remaining(), 11);
+        p.clear_display();
+        assert_eq!(p.display_remaining(), 16);
+        p.set_display_data("0123456789abcdef");
+        assert_eq!(p.display_remaining(), 0);
+        p.set_display_data_raw(b"abc\0").unwrap();
+        assert!(p.validate().is_ok());
+        assert_eq!(p.display_remaining(), 13);
+    }
+
+    #[test]
+    fn test_is_valid_display() {
+        assert!(is_valid_display(""));
+        assert!(is_valid_display("hello"));
+        assert!(is_valid_display("0123456789abcdef"));
+        assert!(is_valid_display("hi\0\0"));
+        assert!(!is_valid_display("0123456789abcdefg"));
+        assert!(!is_valid_display("hi 👋"));
+        assert!(!is_valid_display("é"));
+        assert!(!is_valid_display("tab\there"));
+        assert!(!is_valid_display("line\n"));
+        assert!(!is_valid_display("hi\0there"));
+
+        assert!(is_valid_display_bytes(b"hello\0\0"));
+        assert!(!is_valid_display_bytes(&[b'a', 0x1b]));
+        assert!(!is_valid_display_bytes(&[0xff]));
+    }
+
+    #[test]
+    fn test_stream_decoder() {
+        let mut other = TSL31Packet::new_unchecked(VALID_RAW);
+        other.set_address(0x02).unwrap();
+        let mut stream = [0u8; 3 * PACKET_LENGTH_31 + 8];
+        // Garbage, including a byte that looks like an address, before the first packet
+        stream[..3].copy_from_slice(&[0x01, 0x85, 0x20]);
+        stream[3..21].copy_from_slice(&VALID_RAW);
+        // A truncated packet followed by a whole one
+        stream[21..26].copy_from_slice(&VALID_RAW[..5]);
+        stream[26..44].copy_from_slice(&other.to_bytes());
+        stream[44..62].copy_from_slice(&VALID_RAW);
+
+        let mut decoder = StreamDecoder::new();
+        let mut input = &stream[..];
+        assert_eq!(
+            decoder.next_packet(&mut input),
+            Some(TSL31Packet::new_unchecked(VALID_RAW))
+        );
+        assert_eq!(decoder.next_packet(&mut input), Some(other));
+        assert_eq!(
+            decoder.next_packet(&mut input),
+            Some(TSL31Packet::new_unchecked(VALID_RAW))
+        );
+        assert_eq!(decoder.next_packet(&mut input), None);
+        assert!(input.is_empty());
+        assert_eq!(decoder.buffered(), 0);
+
+        // Packets split across reads
+        for chunk in VALID_RAW.chunks(5) {
+            let mut input = chunk;
+            let expected = (chunk.len() < 5).then(|| TSL31Packet::new_unchecked(VALID_RAW));
+            assert_eq!(decoder.next_packet(&mut input), expected);
+        }
+        decoder.next_packet(&mut &VALID_RAW[..4]);
+        assert_eq!(decoder.buffered(), 4);
+        decoder.reset();
+        assert_eq!(decoder.buffered(), 0);
+    }
+
+    #[test]
+    fn test_stream_decoder_control_high_bit() {
+        // Only the address byte may have its high bit set, so this isn't a packet
+        let mut bad = VALID_RAW;
+        bad[1] = 0xB1;
+        let mut stream = [0u8; 2 * PACKET_LENGTH_31];
+        stream[..18].copy_from_slice(&bad);
+        stream[18..].copy_from_slice(&VALID_RAW);
+
+        let mut decoder = StreamDecoder::new();
+        let mut input = &stream[..];
+        assert_eq!(
+            decoder.next_packet(&mut input),
+            Some(TSL31Packet::new_unchecked(VALID_RAW))
+        );
+        assert_eq!(decoder.next_packet(&mut input), None);
+    }
+
+    #[test]
+    fn test_stream_decoder_stop_early() {
+        let mut stream = [0u8; 2 * PACKET_LENGTH_31];
+        stream[..18].copy_from_slice(&VALID_RAW);
+        let mut other = TSL31Packet::new_unchecked(VALID_RAW);
+        other.set_address(0x01).unwrap();
+        stream[18..].copy_from_slice(&other.to_bytes());
+
+        // Taking only the first packet leaves the second in the input for the next call
+        let mut decoder = StreamDecoder::new();
+        let mut input = &stream[..];
+        assert_eq!(
+            decoder.next_packet(&mut input),
+            Some(TSL31Packet::new_unchecked(VALID_RAW))
+        );
+        assert_eq!(input, &other.to_bytes()[..]);
+        assert_eq!(decoder.next_packet(&mut input), Some(other));
+        assert!(input.is_empty());
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert!(DisplayWidth::Four.fits("cam1"));
+        assert!(DisplayWidth::Four.fits("cam1    "));
+        assert!(!DisplayWidth::Four.fits("cam 1"));
+        assert!(DisplayWidth::Eight.fits("camera 1"));
+        assert!(!DisplayWidth::Eight.fits("camera 10"));
+        assert!(DisplayWidth::Sixteen.fits("0123456789abcdef"));
+        assert!(!DisplayWidth::Sixteen.fits("0123456789abcdefg"));
+
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert!(!p.display_fits(DisplayWidth::Four));
+        assert!(p.display_fits(DisplayWidth::Eight));
+        assert!(p.display_fits(DisplayWidth::Sixteen));
+        p.set_display_data("0123456789");
+        assert!(!p.display_fits(DisplayWidth::Eight));
+        assert!(p.display_fits(DisplayWidth::Sixteen));
+    }
+
+    #[test]
+    fn test_summary() {
+        use std::string::ToString;
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(
+            p.summary().to_string(),
+            "address:    105\n\
+             tally 1:    on\n\
+             tally 2:    off\n\
+             tally 3:    off\n\
+             tally 4:    on\n\
+             brightness: 1/7\n\
+             display:    \"hello\""
+        );
+        p.set_broadcast_address();
+        assert!(
+            p.summary()
+                .to_string()
+                .starts_with("address:    127 (broadcast)\n")
+        );
+    }
+
+    #[test]
+    fn test_brightness_f32() {
+        assert_eq!(f32::from(Brightness::Zero), 0.0);
+        assert!((f32::from(Brightness::OneSeventh) - 0.143).abs() < 0.001);
+        assert_eq!(f32::from(Brightness::OneHalf), 0.5);
+        assert_eq!(f32::from(Brightness::Full), 1.0);
+        for (val, expected) in [
+            (f32::NAN, Brightness::Zero),
+            (-1.0, Brightness::Zero),
+            (0.0, Brightness::Zero),
+            (0.07, Brightness::Zero),
+            // Midpoint of 0 and 1/7
+            (1.0 / 14.0, Brightness::OneSeventh),
+            (1.0 / 7.0, Brightness::OneSeventh),
+            (0.32, Brightness::OneSeventh),
+            // Midpoint of 1/7 and 1/2
+            (9.0 / 28.0, Brightness::OneHalf),
+            (0.5, Brightness::OneHalf),
+            (0.749, Brightness::OneHalf),
+            (0.75, Brightness::Full),
+            (1.0, Brightness::Full),
+            (2.0, Brightness::Full),
+        ] {
+            assert_eq!(Brightness::from_f32_nearest(val), expected, "{val}");
+        }
+    }
+
+    #[test]
+    fn test_brightness_percent() {
+        for b in Brightness::all() {
+            assert_eq!(Brightness::from_percent_nearest(b.as_percent()), b);
+        }
+        for (percent, expected) in [
+            (6, Brightness::Zero),
+            (7, Brightness::OneSeventh),
+            (25, Brightness::OneSeventh),
+            (32, Brightness::OneHalf),
+            (74, Brightness::OneHalf),
+            (75, Brightness::Full),
+            (255, Brightness::Full),
+        ] {
+            assert_eq!(
+                Brightness::from_percent_nearest(percent),
+                expected,
+                "{percent}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_control() {
+        let c = Control::from_byte(VALID_RAW[fields::CONTROL]);
+        assert_eq!(c.to_byte(), VALID_RAW[fields::CONTROL]);
+        assert!(c.contains(Control::TALLY_1 | Control::TALLY_4));
+        assert!(!c.contains(Control::TALLY_2));
+        assert_eq!(c.tally().to_array(), [true, false, false, true]);
+        assert_eq!(c.brightness(), Brightness::OneSeventh);
+        assert_eq!(c.brightness_bits(), 0b01);
+        assert_eq!(
+            (Control::TALLY_1 | Control::TALLY_3).to_byte(),
+            Tally::from_array([true, false, true, false]).bits()
+        );
+
+        // Byte compatible with the previous masks, and leaves the reserved bits alone
+        for b in 0..=0xff {
+            let c = Control::from_byte(b);
+            for bits in 0..=0x0f {
+                let tally = Tally::from_bits(bits);
+                assert_eq!(c.with_tally(tally).to_byte(), (b & 0xf0) | bits);
+            }
+            for (bits, brightness) in Brightness::all().into_iter().enumerate() {
+                let expected = (b & !0x30) | (bits as u8) << 4;
+                assert_eq!(c.with_brightness(brightness).to_byte(), expected);
+                assert_eq!(c.with_brightness_bits(bits as u8).to_byte(), expected);
+            }
+            assert_eq!(c.brightness_bits(), (b >> 4) & 0x3);
+        }
+    }
+
+    #[test]
+    fn test_toggle_all_tally() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(p.with_all_tally_toggled(), [false, true, true, false]);
+        assert_eq!(p.tally(), [true, false, false, true]);
+        p.toggle_all_tally();
+        assert_eq!(p.tally(), [false, true, true, false]);
+        assert_eq!(p.brightness(), Brightness::OneSeventh);
+        p.toggle_all_tally();
+        assert_eq!(p.inner(), VALID_RAW);
+    }
+
+    #[test]
+    fn test_tally_mask() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        assert_eq!(p.tally_mask(), 0b1001);
+        for mask in 0x0..=0xf {
+            p.set_tally_mask(mask);
+            assert_eq!(p.tally_mask(), mask);
+            assert_eq!(p.tally(), Tally::from_bits(mask).to_array());
+            assert_eq!(p.brightness(), Brightness::OneSeventh);
+            p.set_tally_mask(mask | 0xf0);
+            assert_eq!(p.tally_mask(), mask);
+            assert_eq!(p.brightness(), Brightness::OneSeventh);
+        }
+    }
+
+    #[test]
+    fn test_new_zeroed() {
+        let mut p = TSL31Packet::new_zeroed();
+        assert_eq!(p.to_bytes().len(), PACKET_LENGTH_31);
+        assert_eq!(p.to_bytes(), [0; PACKET_LENGTH_31]);
+        assert_eq!(p.validate(), Err(Error::AddressInvalid));
+        p.set_address(1).unwrap();
+        assert!(p.validate().is_ok());
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let p = TSL31Packet::heartbeat(0x12).unwrap();
+        assert!(p.validate().is_ok());
+        assert_eq!(p.address(), 0x12);
+        assert_eq!(p.tally(), [false; 4]);
+        assert!(!p.any_tally_on());
+        assert_eq!(p.brightness(), Brightness::Full);
+        assert_eq!(p.display_data(), "");
+
+        assert!(
+            TSL31Packet::heartbeat(BROADCAST_ADDRESS)
+                .unwrap()
+                .is_broadcast()
+        );
+        assert_eq!(
+            TSL31Packet::heartbeat(0x80),
+            Err(Error::AddressOutOfRange { got: 0x80 })
+        );
+    }
+
+    #[test]
+    fn test_from_gpio() {
+        let p = TSL31Packet::from_gpio(0x05, true, false, Brightness::Full).unwrap();
+        assert_eq!(p.address(), 0x05);
+        assert_eq!(p.tally(), [true, false, false, false]);
+        assert_eq!(p.brightness(), Brightness::Full);
+        assert_eq!(p.display_data_raw(), [b' '; 16]);
+        assert!(TSL31Packet::new_checked(p.to_bytes()).is_ok());
+
+        let p = TSL31Packet::from_gpio(0x05, false, true, Brightness::OneHalf).unwrap();
+        assert_eq!(p.tally(), [false, true, false, false]);
+        assert_eq!(p.brightness(), Brightness::OneHalf);
+        let p = TSL31Packet::from_gpio(0x05, true, true, Brightness::Full).unwrap();
+        assert_eq!(p.tally(), [true, true, false, false]);
+
+        assert_eq!(
+            TSL31Packet::from_gpio(0x80, true, false, Brightness::Full),
+            Err(Error::AddressOutOfRange { got: 0x80 })
+        );
+    }
+
+    #[test]
+    fn test_tally_intensities() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        p.set_brightness(Brightness::OneHalf);
+        assert_eq!(p.tally_intensities(), [0.5, 0.0, 0.0, 0.5]);
+        p.set_brightness(Brightness::Zero);
+        assert_eq!(p.tally_intensities(), [0.0; 4]);
+        p.set_control([true; 4], Brightness::Full);
+        assert_eq!(p.tally_intensities(), [1.0; 4]);
+    }
+
+    #[test]
+    fn test_view() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(
+            p.view(),
+            PacketView {
+                address: 0x69,
+                tally: [true, false, false, true],
+                brightness: Brightness::OneSeventh,
+                display: "hello",
+            }
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let a = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert!(a.diff(&a).is_empty());
+
+        let mut raw = VALID_RAW;
+        raw[fields::CONTROL] ^= 0b100;
+        let b = TSL31Packet::new_checked(raw).unwrap();
+        assert_eq!(
+            a.diff(&b),
+            PacketDiff {
+                tally: [false, false, true, false],
+                ..Default::default()
+            }
+        );
+
+        // Padding differences are ignored
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start + 5..].fill(0);
+        let c = TSL31Packet::new_checked(raw).unwrap();
+        assert_ne!(a, c);
+        assert!(a.diff(&c).is_empty());
+    }
+
+    #[test]
+    fn test_eq_logical() {
+        let a = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start + 5..].fill(0);
+        let b = TSL31Packet::new_checked(raw).unwrap();
+        assert_ne!(a, b);
+        assert!(a.eq_logical(&b));
+
+        raw[fields::CONTROL] = 0;
+        assert!(!a.eq_logical(&TSL31Packet::new_checked(raw).unwrap()));
+    }
+
+    #[test]
+    fn test_const_getters() {
+        const P: TSL31Packet<[u8; PACKET_LENGTH_31]> = TSL31Packet::new_unchecked(VALID_RAW);
+        const _: u8 = P.address_const();
+        const _: () = assert!(P.address_const() == 0x69);
+        const _: () = assert!(P.control_byte_const() == 0b00011001);
+        const TALLY: [bool; 4] = P.tally_const();
+        const BRIGHTNESS: Brightness = P.brightness_const();
+        assert_eq!(TALLY, P.tally());
+        assert_eq!(BRIGHTNESS, P.brightness());
+    }
+
+    #[test]
+    fn test_display_data_untrimmed() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(p.display_data_untrimmed(), "hello           ");
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start + 7..].fill(0);
+        let p = TSL31Packet::new_checked(raw).unwrap();
+        assert_eq!(p.display_data_untrimmed(), "hello  ");
+        assert_eq!(p.display_data(), "hello");
+    }
+
+    #[test]
+    fn test_display_data_raw() {
+        let p = TSL31Packet::new_checked(VALID_RAW).unwrap();
+        assert_eq!(p.display_data_raw(), b"hello           ");
+    }
+
+    #[test]
+    fn test_set_display_data() {
+        let buf = [0u8; PACKET_LENGTH_31];
         let mut p = TSL31Packet::new_unchecked(buf);
         for s in ["", "hello there", "1234567890=+!)()"] {
             p.set_display_data(s);
             assert_eq!(p.display_data(), s);
         }
     }
+
+    #[test]
+    fn test_set_display_data_null_padded() {
+        let mut raw = VALID_RAW;
+        raw[fields::DISPLAY_DATA.start + 5..].fill(0);
+        let p = TSL31Packet::new_checked(raw).unwrap();
+        let text = str::from_utf8(p.display_data_raw()).unwrap();
+
+        let mut q = TSL31Packet::new_unchecked(VALID_RAW);
+        q.set_display_data("a longer label");
+        q.set_display_data(text);
+        assert_eq!(q.display_data(), "hello");
+        let q = TSL31Packet::new_checked(q.inner()).unwrap();
+        assert_eq!(q, p);
+
+        // Short null-padded strings are null-padded to the end of the field
+        let mut q = TSL31Packet::new_unchecked(VALID_RAW);
+        q.set_display_data("hi\0");
+        assert_eq!(
+            q.display_data_raw(),
+            &[b'h', b'i', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_display_data_interior_null() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        p.set_display_data("h\0i");
+    }
+
+    #[test]
+    fn test_set_display_data_shorter() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        p.set_display_data("hello");
+        p.set_display_data("hi");
+        assert_eq!(p.display_data(), "hi");
+        p.clear_display();
+        assert_eq!(&p.inner()[fields::DISPLAY_DATA], &[b' '; 16]);
+    }
+
+    #[test]
+    fn test_set_display_data_reused_buffer() {
+        let mut p = TSL31Packet::new_unchecked(VALID_RAW);
+        p.set_display_data("LONGLABEL");
+        p.set_display_data("A");
+        assert_eq!(p.display_data(), "A");
+        assert!(TSL31Packet::new_checked(p.inner()).is_ok());
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn test_set_display_data_owned() {
+        let mut p = TSL31Packet::new_unchecked([0u8; PACKET_LENGTH_31]);
+        let s: heapless::String<16> = "hello".try_into().unwrap();
+        p.set_display_data(&s);
+        assert_eq!(p.display_data(), "hello");
+        p.set_display_data(s);
+        assert_eq!(p.display_data(), "hello");
+    }
+
+    static ALL_BRIGHTNESS: [Brightness; 4] = Brightness::all();
+
+    proptest! {
+        #[test]
+        fn prop_round_trip(
+            address in 0u8..=BROADCAST_ADDRESS,
+            tally in any::<[bool; 4]>(),
+            brightness in prop::sample::select(&ALL_BRIGHTNESS[..]),
+            display in "[\x20-\x7f]{0,16}",
+            previous in "[\x20-\x7f]{0,16}",
+        ) {
+            // Start from a buffer holding another display string to catch stale bytes
+            let mut p = TSL31Packet::default();
+            p.set_display_data(&previous);
+            p.set_address(address).unwrap();
+            p.set_tally(tally);
+            p.set_brightness(brightness);
+            p.set_display_data(&display);
+
+            let p = TSL31Packet::new_checked(p.inner()).unwrap();
+            prop_assert_eq!(p.address(), address);
+            prop_assert_eq!(p.tally(), tally);
+            prop_assert_eq!(p.brightness(), brightness);
+            prop_assert_eq!(p.display_data(), display.trim_end());
+        }
+    }
 }
@@ -0,0 +1,52 @@
+//! Round-trip tests against raw packet captures in `tests/captures/`.
+//!
+//! Each `.bin` file holds the bytes of a single v3.1 packet. Every capture must parse, and
+//! re-encoding its fields into a fresh packet must reproduce it byte for byte, apart from display
+//! padding, which is always re-encoded as spaces. To add a regression test for a device, drop a
+//! capture of one of its packets into the directory.
+#![cfg(feature = "v3_1")]
+
+use std::{fs, path::Path};
+
+use tsl_umd::v3_1::TSL31Packet;
+
+/// Load every `.bin` file in `dir`, sorted by name so failures are reproducible
+fn load_captures(dir: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut captures: Vec<_> = fs::read_dir(dir)
+        .expect("captures directory must exist")
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "bin"))
+        .map(|p| {
+            let name = p.file_name().unwrap().to_string_lossy().into_owned();
+            (name, fs::read(&p).unwrap())
+        })
+        .collect();
+    captures.sort();
+    captures
+}
+
+#[test]
+fn captures_round_trip() {
+    let captures = load_captures(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/captures"));
+    assert!(!captures.is_empty(), "no captures found");
+    for (name, bytes) in captures {
+        let p = TSL31Packet::new_checked(&bytes[..])
+            .unwrap_or_else(|e| panic!("{name} failed to parse: {e}"));
+
+        // Rebuild through the typed setters, so the packing of every field is checked
+        let mut out = TSL31Packet::default();
+        out.set_address(p.address()).unwrap();
+        out.set_tally(p.tally());
+        out.set_brightness(p.brightness());
+        out.set_display_data(p.display_data());
+
+        // The setters always pad with spaces, so compare against the capture padded the same way
+        let mut expected = TSL31Packet::new_unchecked(bytes.clone());
+        expected.normalize_padding();
+        assert_eq!(
+            out.inner()[..],
+            expected.inner()[..],
+            "{name} didn't round-trip"
+        );
+    }
+}